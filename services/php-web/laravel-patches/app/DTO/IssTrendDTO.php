@@ -18,6 +18,7 @@ class IssTrendDTO
         public readonly ?float $from_lon = null,
         public readonly ?float $to_lat = null,
         public readonly ?float $to_lon = null,
+        public readonly ?array $stats = null,
     ) {}
 
     public static function fromArray(array $data): self
@@ -33,6 +34,7 @@ class IssTrendDTO
             from_lon: isset($data['from_lon']) ? (float)$data['from_lon'] : null,
             to_lat: isset($data['to_lat']) ? (float)$data['to_lat'] : null,
             to_lon: isset($data['to_lon']) ? (float)$data['to_lon'] : null,
+            stats: isset($data['stats']) && is_array($data['stats']) ? $data['stats'] : null,
         );
     }
 
@@ -117,6 +119,7 @@ class IssTrendDTO
             'from_lon' => $this->from_lon,
             'to_lat' => $this->to_lat,
             'to_lon' => $this->to_lon,
+            'stats' => $this->stats,
         ];
     }
 }
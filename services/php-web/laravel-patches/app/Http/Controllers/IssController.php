@@ -9,7 +9,8 @@ class IssController extends Controller
         $base = env('RUST_ISS_BASE', 'http://rust_iss:3000');
 
         $last  = @file_get_contents($base.'/last');
-        $trend = @file_get_contents($base.'/iss/trend');
+        $window = max(1, min(1440, (int) request('window', 30)));
+        $trend = @file_get_contents($base.'/iss/trend?window='.$window);
 
         $lastJson  = $last  ? json_decode($last,  true) : [];
         $trendJson = $trend ? json_decode($trend, true) : [];
@@ -22,6 +23,6 @@ class IssController extends Controller
             $trendJson = $trendJson['data'];
         }
 
-        return view('iss', ['last' => $lastJson, 'trend' => $trendJson, 'base' => $base]);
+        return view('iss', ['last' => $lastJson, 'trend' => $trendJson, 'base' => $base, 'window' => $window]);
     }
 }
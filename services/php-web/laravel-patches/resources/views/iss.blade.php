@@ -90,6 +90,17 @@
               <tr><td class="text-muted">Интервал</td><td>{{ number_format($trend['dt_sec'] ?? 0, 1) }} сек</td></tr>
               <tr><td class="text-muted">Скорость</td><td>{{ number_format($trend['velocity_kmh'] ?? 0, 2) }} км/ч</td></tr>
             </table>
+            @if(!empty($trend['stats']))
+              @php($stats = $trend['stats'])
+              <h6 class="mt-3">За {{ $window }} мин ({{ $stats['samples'] ?? 0 }} замеров)</h6>
+              <table class="table table-sm">
+                <tr><td class="text-muted">Путь</td><td>{{ number_format($stats['ground_distance_km'] ?? 0, 1) }} км</td></tr>
+                <tr><td class="text-muted">Ср. / макс. скорость</td><td>{{ number_format($stats['avg_ground_speed_kmh'] ?? 0, 0) }} / {{ number_format($stats['max_ground_speed_kmh'] ?? 0, 0) }} км/ч</td></tr>
+                <tr><td class="text-muted">Высота мин / ср / макс</td><td>{{ number_format($stats['altitude_min_km'] ?? 0, 1) }} / {{ number_format($stats['altitude_mean_km'] ?? 0, 1) }} / {{ number_format($stats['altitude_max_km'] ?? 0, 1) }} км</td></tr>
+                <tr><td class="text-muted">Курс</td><td>{{ isset($stats['heading_deg']) ? number_format($stats['heading_deg'], 0).'°' : '—' }}</td></tr>
+                <tr><td class="text-muted">Пропуски</td><td>{{ count($stats['gaps'] ?? []) }}</td></tr>
+              </table>
+            @endif
           @else
             <div class="text-muted">Нет данных</div>
           @endif
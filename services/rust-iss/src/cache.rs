@@ -143,16 +143,74 @@ impl CacheClient {
 
 /// Ключи кэша
 pub mod cache_keys {
-    pub fn iss_latest() -> &'static str {
-        "iss:latest"
+    pub fn iss_latest(norad_id: i32) -> String {
+        format!("iss:latest:{}", norad_id)
     }
 
-    pub fn iss_trend() -> &'static str {
-        "iss:trend"
+    pub fn iss_trend(norad_id: i32, window_min: i64) -> String {
+        format!("iss:trend:{}:{}", norad_id, window_min)
     }
 
-    pub fn osdr_list(limit: i64) -> String {
-        format!("osdr:list:{}", limit)
+    pub fn iss_trend_prefix(norad_id: i32) -> String {
+        format!("iss:trend:{}:", norad_id)
+    }
+
+    pub fn iss_history(norad_id: i32, from: i64, to: i64, step: i64) -> String {
+        format!("iss:history:{}:{}:{}:{}", norad_id, from, to, step)
+    }
+
+    pub fn iss_illumination(norad_id: i32) -> String {
+        format!("iss:illumination:{}", norad_id)
+    }
+
+    /// Координаты уже округлены сервисом — ключ общий для соседних наблюдателей
+    pub fn iss_passes(lat: f64, lon: f64, alt_m: f64, days: i64) -> String {
+        format!("iss:passes:{:.1}:{:.1}:{:.0}:{}", lat, lon, alt_m, days)
+    }
+
+    /// Все параметры списка входят в ключ; строки в Debug-виде (в кавычках),
+    /// поэтому `:` внутри поиска не склеит разные запросы
+    pub fn osdr_list(filter: &crate::domain::OsdrListFilter, include_total: bool) -> String {
+        format!(
+            "osdr:list:{}:{}:{}:{:?}:{:?}:{}:{}:{}:{}",
+            filter.limit,
+            filter.sort_by.as_str(),
+            if filter.descending { "desc" } else { "asc" },
+            filter.status,
+            filter.search,
+            filter.after.as_ref().map(|c| c.encode()).unwrap_or_default(),
+            include_total,
+            facet_key(&filter.facets),
+            filter.include_withdrawn,
+        )
+    }
+
+    /// Сортировка и курсор на агрегаты не влияют — в ключе только фильтры и лимит
+    pub fn osdr_facets(filter: &crate::domain::OsdrListFilter) -> String {
+        format!(
+            "osdr:facets:{}:{:?}:{:?}:{}:{}",
+            filter.limit,
+            filter.status,
+            filter.search,
+            facet_key(&filter.facets),
+            filter.include_withdrawn,
+        )
+    }
+
+    fn facet_key(facets: &crate::domain::OsdrFacetFilter) -> String {
+        format!(
+            "{:?}:{:?}:{:?}:{:?}",
+            facets.organism, facets.project_type, facets.assay_technology, facets.release_year
+        )
+    }
+
+    /// Под префиксом osdr: — сбрасывается вместе со списками при sync
+    pub fn osdr_item(dataset_id: &str) -> String {
+        format!("osdr:item:{}", dataset_id)
+    }
+
+    pub fn osdr_history(dataset_id: &str, include_raw: bool) -> String {
+        format!("osdr:history:{}:{}", dataset_id, include_raw)
     }
 
     pub fn osdr_count() -> &'static str {
@@ -170,8 +228,4 @@ pub mod cache_keys {
     pub fn osdr_prefix() -> &'static str {
         "osdr:"
     }
-
-    pub fn space_prefix() -> &'static str {
-        "space:"
-    }
 }
@@ -60,9 +60,9 @@ impl ApiClient {
         &self.config
     }
 
-    /// Получить данные с ISS API
-    pub async fn fetch_iss(&self) -> Result<Value, ApiError> {
-        let url = self.config.where_iss_url.clone();
+    /// Получить текущее положение спутника по NORAD ID
+    pub async fn fetch_iss(&self, norad_id: i32) -> Result<Value, ApiError> {
+        let url = self.config.satellite_url(norad_id);
         let client = self.client.clone();
         
         self.fetch_with_retry(
@@ -87,8 +87,36 @@ impl ApiClient {
         .await
     }
 
-    /// Получить данные из NASA OSDR Search API
-    pub async fn fetch_osdr(&self) -> Result<Value, ApiError> {
+    /// Получить актуальный TLE (двухстрочные элементы орбиты) МКС
+    pub async fn fetch_tle(&self) -> Result<Value, ApiError> {
+        let url = self.config.tle_url.clone();
+        let client = self.client.clone();
+        
+        self.fetch_with_retry(
+            || {
+                let url = url.clone();
+                let client = client.clone();
+                async move {
+                    let resp = client.get(&url).send().await?;
+                    
+                    if !resp.status().is_success() {
+                        return Err(ApiError::upstream_error(format!(
+                            "TLE API returned {}",
+                            resp.status()
+                        )));
+                    }
+                    
+                    resp.json().await.map_err(ApiError::from)
+                }
+            },
+            self.config.http_max_retries,
+        )
+        .await
+    }
+
+    /// Получить страницу NASA OSDR Search API (`from`/`size` как в Elasticsearch).
+    /// Свежие исследования первыми — на этом держится ранняя остановка инкрементального режима
+    pub async fn fetch_osdr(&self, from: i64, size: u32) -> Result<Value, ApiError> {
         let url = self.config.nasa_api_url.clone();
         let api_key = self.config.nasa_api_key.clone();
         let client = self.client.clone();
@@ -101,8 +129,9 @@ impl ApiClient {
                 async move {
                     let mut req = client.get(&url)
                         .query(&[("type", "cgene")])  // Search only NASA OSDR (cgene)
-                        .query(&[("from", "0")])
-                        .query(&[("size", "25")]);
+                        .query(&[("from", from.to_string())])
+                        .query(&[("size", size.to_string())])
+                        .query(&[("sort", "Study Public Release Date"), ("order", "desc")]);
                     
                     if !api_key.is_empty() {
                         req = req.query(&[("api_key", &api_key)]);
@@ -1,4 +1,9 @@
+use crate::domain::{OsdrSyncMode, RetentionPolicy};
 use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// NORAD ID МКС — спутник по умолчанию для всех ISS-эндпоинтов
+pub const ISS_NORAD_ID: i32 = 25544;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -6,7 +11,25 @@ pub struct Config {
     pub nasa_api_url: String,
     pub nasa_api_key: String,
     pub where_iss_url: String,
+    pub tle_url: String,
     pub redis_url: String,
+
+    // Постраничная синхронизация OSDR
+    pub osdr_page_size: u32,
+    pub osdr_max_pages: u32,
+    pub osdr_sync_mode: OsdrSyncMode,
+    // Как часто планировщик делает полный обход (нужен для пометки withdrawn), секунды
+    pub osdr_full_sync_every_seconds: u64,
+
+    // Хранение iss_fetch_log и space_cache (политики `raw_days:bucket_secs:keep_days`)
+    pub retention_every_seconds: u64,
+    pub retention_batch_size: i64,
+    pub iss_retention: RetentionPolicy,
+    pub space_retention: RetentionPolicy,
+    pub space_retention_overrides: BTreeMap<String, RetentionPolicy>,
+
+    // Спутники (NORAD ID), которые опрашивает планировщик
+    pub tracked_norad_ids: Vec<i32>,
     
     // JWST API
     pub jwst_api_url: String,
@@ -22,10 +45,16 @@ pub struct Config {
     pub donki_every_seconds: u64,
     pub spacex_every_seconds: u64,
     pub jwst_every_seconds: u64,
+    pub tle_every_seconds: u64,
     
     // Rate limiting
     pub rate_limit_requests: u32,
-    pub rate_limit_window_secs: u32,
+
+    // Максимум одновременных SSE-подключений к /iss/stream на весь экземпляр (общий, не на клиента/IP)
+    pub sse_max_connections: usize,
+
+    // Максимум одновременных выгрузок /osdr/export.*
+    pub osdr_export_max_concurrent: usize,
     
     // HTTP таймауты и retry
     pub http_timeout_secs: u64,
@@ -38,9 +67,15 @@ pub struct Config {
 }
 
 impl Config {
+    /// Конфиг из переменных процесса и `.env`; тесты `.env` разработчика не читают
     pub fn from_env() -> Self {
+        #[cfg(not(test))]
         dotenvy::dotenv().ok();
-        
+        Self::from_process_env()
+    }
+
+    /// Собрать конфиг только из переменных процесса (без чтения .env)
+    pub fn from_process_env() -> Self {
         Self {
             database_url: std::env::var("DATABASE_URL")
                 .expect("DATABASE_URL is required"),
@@ -49,8 +84,25 @@ impl Config {
             nasa_api_key: std::env::var("NASA_API_KEY").unwrap_or_default(),
             where_iss_url: std::env::var("WHERE_ISS_URL")
                 .unwrap_or_else(|_| "https://api.wheretheiss.at/v1/satellites/25544".to_string()),
+            tle_url: std::env::var("TLE_URL")
+                .unwrap_or_else(|_| "https://api.wheretheiss.at/v1/satellites/25544/tles".to_string()),
             redis_url: std::env::var("REDIS_URL")
                 .unwrap_or_else(|_| "redis://redis:6379".to_string()),
+            tracked_norad_ids: env_norad_ids("TRACKED_NORAD_IDS"),
+
+            osdr_page_size: env_u32("OSDR_PAGE_SIZE", 100).clamp(1, 500),
+            osdr_max_pages: env_u32("OSDR_MAX_PAGES", 50).max(1),
+            osdr_sync_mode: std::env::var("OSDR_SYNC_MODE")
+                .ok()
+                .and_then(|s| OsdrSyncMode::parse(s.trim()))
+                .unwrap_or(OsdrSyncMode::Incremental),
+            osdr_full_sync_every_seconds: env_u64("OSDR_FULL_SYNC_EVERY_SECONDS", 86400).max(600),
+
+            retention_every_seconds: env_u64("RETENTION_EVERY_SECONDS", 3600).max(60),
+            retention_batch_size: env_u64("RETENTION_BATCH_SIZE", 5000).clamp(100, 50_000) as i64,
+            iss_retention: env_policy("ISS_RETENTION", "7:600:90"),
+            space_retention: env_policy("SPACE_RETENTION", "1:86400:0"),
+            space_retention_overrides: env_policy_overrides("SPACE_RETENTION_OVERRIDES"),
 
             jwst_api_url: std::env::var("JWST_HOST")
                 .unwrap_or_else(|_| "https://api.jwstapi.com".to_string()),
@@ -65,9 +117,11 @@ impl Config {
             donki_every_seconds: env_u64("DONKI_EVERY_SECONDS", 3600),
             spacex_every_seconds: env_u64("SPACEX_EVERY_SECONDS", 3600),
             jwst_every_seconds: env_u64("JWST_EVERY_SECONDS", 3600),
+            tle_every_seconds: env_u64("TLE_EVERY_SECONDS", 21600),
             
             rate_limit_requests: env_u32("RATE_LIMIT_REQUESTS", 100),
-            rate_limit_window_secs: env_u32("RATE_LIMIT_WINDOW_SECS", 60),
+            sse_max_connections: env_u64("SSE_MAX_CONNECTIONS", 100) as usize,
+            osdr_export_max_concurrent: env_u64("OSDR_EXPORT_MAX_CONCURRENT", 4).max(1) as usize,
             
             http_timeout_secs: env_u64("HTTP_TIMEOUT_SECS", 30),
             http_connect_timeout_secs: env_u64("HTTP_CONNECT_TIMEOUT_SECS", 10),
@@ -77,6 +131,71 @@ impl Config {
             db_min_idle: std::env::var("DB_MIN_IDLE").ok().and_then(|s| s.parse().ok()),
         }
     }
+
+    /// URL текущего положения спутника: `{norad_id}` в WHERE_ISS_URL заменяется на ID,
+    /// а у URL вида `.../satellites/25544` подменяется последний сегмент
+    pub fn satellite_url(&self, norad_id: i32) -> String {
+        if self.where_iss_url.contains("{norad_id}") {
+            return self.where_iss_url.replace("{norad_id}", &norad_id.to_string());
+        }
+        match self.where_iss_url.trim_end_matches('/').rsplit_once('/') {
+            Some((base, last)) if last.parse::<i64>().is_ok() => format!("{}/{}", base, norad_id),
+            _ => self.where_iss_url.clone(),
+        }
+    }
+
+    pub fn is_tracked(&self, norad_id: i32) -> bool {
+        self.tracked_norad_ids.contains(&norad_id)
+    }
+
+    /// Политика хранения снимков источника space_cache
+    pub fn space_retention_for(&self, source: &str) -> RetentionPolicy {
+        self.space_retention_overrides
+            .get(source)
+            .copied()
+            .unwrap_or(self.space_retention)
+    }
+}
+
+/// Политика хранения из переменной; некорректное значение — политика по умолчанию
+fn env_policy(key: &str, default: &str) -> RetentionPolicy {
+    std::env::var(key)
+        .ok()
+        .and_then(|s| RetentionPolicy::parse(&s))
+        .or_else(|| RetentionPolicy::parse(default))
+        .expect("default retention policy is valid")
+}
+
+/// Переопределения по источникам: `neo=3:86400:180,tle=7:86400:30`; некорректные пары пропускаются
+fn env_policy_overrides(key: &str) -> BTreeMap<String, RetentionPolicy> {
+    std::env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| {
+            let (source, policy) = pair.split_once('=')?;
+            let source = source.trim();
+            (!source.is_empty()).then_some(())?;
+            Some((source.to_string(), RetentionPolicy::parse(policy)?))
+        })
+        .collect()
+}
+
+/// Список NORAD ID через запятую; пустой или некорректный список — только МКС
+fn env_norad_ids(key: &str) -> Vec<i32> {
+    let mut ids: Vec<i32> = Vec::new();
+    for id in std::env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|s| s.trim().parse::<i32>().ok())
+    {
+        if id > 0 && !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.is_empty() {
+        ids.push(ISS_NORAD_ID);
+    }
+    ids
 }
 
 fn env_u64(key: &str, default: u64) -> u64 {
@@ -0,0 +1,100 @@
+use crate::domain::JsonPatchSummary;
+use serde_json::{json, Value};
+
+/// Сколько операций расписывается в сводке построчно
+const SUMMARY_MAX_CHANGES: usize = 100;
+/// Длина значения в строке сводки, символов
+const SUMMARY_VALUE_CHARS: usize = 80;
+
+/// JSON Patch (RFC 6902), превращающий `old` в `new`: только `add`, `remove`, `replace`.
+/// Объекты сравниваются по ключам, массивы — поэлементно по индексу.
+pub fn json_patch(old: &Value, new: &Value) -> Vec<Value> {
+    let mut ops = Vec::new();
+    diff_into(&mut ops, "", old, new);
+    ops
+}
+
+fn diff_into(ops: &mut Vec<Value>, path: &str, old: &Value, new: &Value) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, old_value) in a {
+                let child = format!("{}/{}", path, escape_pointer(key));
+                match b.get(key) {
+                    Some(new_value) => diff_into(ops, &child, old_value, new_value),
+                    None => ops.push(json!({"op": "remove", "path": child})),
+                }
+            }
+            for (key, new_value) in b {
+                if !a.contains_key(key) {
+                    let child = format!("{}/{}", path, escape_pointer(key));
+                    ops.push(json!({"op": "add", "path": child, "value": new_value}));
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            let common = a.len().min(b.len());
+            for i in 0..common {
+                diff_into(ops, &format!("{}/{}", path, i), &a[i], &b[i]);
+            }
+            for (i, value) in b.iter().enumerate().skip(common) {
+                ops.push(json!({"op": "add", "path": format!("{}/{}", path, i), "value": value}));
+            }
+            // Удаляем с конца, чтобы индексы оставшихся операций не сдвигались
+            for i in (common..a.len()).rev() {
+                ops.push(json!({"op": "remove", "path": format!("{}/{}", path, i)}));
+            }
+        }
+        _ if old != new => ops.push(json!({"op": "replace", "path": path, "value": new})),
+        _ => {}
+    }
+}
+
+/// Человекочитаемая сводка патча; прежние значения берутся из `old`
+pub fn summarize(old: &Value, patch: &[Value]) -> JsonPatchSummary {
+    let mut summary = JsonPatchSummary::default();
+    for op in patch {
+        let path = op["path"].as_str().unwrap_or_default();
+        let shown = if path.is_empty() { "/" } else { path };
+        let line = match op["op"].as_str() {
+            Some("add") => {
+                summary.added += 1;
+                format!("+ {}: {}", shown, preview(&op["value"]))
+            }
+            Some("remove") => {
+                summary.removed += 1;
+                format!("- {}: {}", shown, preview(old.pointer(path).unwrap_or(&Value::Null)))
+            }
+            Some("replace") => {
+                summary.replaced += 1;
+                format!(
+                    "~ {}: {} → {}",
+                    shown,
+                    preview(old.pointer(path).unwrap_or(&Value::Null)),
+                    preview(&op["value"])
+                )
+            }
+            _ => continue,
+        };
+        if summary.changes.len() < SUMMARY_MAX_CHANGES {
+            summary.changes.push(line);
+        } else {
+            summary.truncated = true;
+        }
+    }
+    summary
+}
+
+/// Значение в одну строку, длинное — с многоточием
+fn preview(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() <= SUMMARY_VALUE_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(SUMMARY_VALUE_CHARS).collect();
+    format!("{}…", cut)
+}
+
+/// Экранирование сегмента JSON Pointer (RFC 6901)
+fn escape_pointer(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
@@ -1,4 +1,6 @@
-use chrono::{DateTime, Utc};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::FromRow;
@@ -7,22 +9,94 @@ use sqlx::FromRow;
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct IssFetchLog {
     pub id: i64,
+    pub norad_id: i32,
     pub fetched_at: DateTime<Utc>,
     pub source_url: String,
     pub payload: Value,
+    /// Телеметрия, разобранная при сохранении (отдельные колонки таблицы)
+    #[sqlx(flatten)]
+    pub position: IssPosition,
 }
 
 impl IssFetchLog {
+    #[cfg(test)]
     pub fn new(source_url: String, payload: Value) -> Self {
         Self {
             id: 0,
+            norad_id: crate::config::ISS_NORAD_ID,
             fetched_at: Utc::now(),
             source_url,
+            position: IssPosition::from_payload(&payload).unwrap_or_default(),
             payload,
         }
     }
 }
 
+/// ISS Position - типизированная телеметрия из ответа wheretheiss.at
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct IssPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    pub velocity: Option<f64>,
+    /// daylight / eclipsed / visible
+    pub visibility: Option<String>,
+    pub footprint: Option<f64>,
+    pub solar_lat: Option<f64>,
+    pub solar_lon: Option<f64>,
+    /// Время замера по данным источника
+    #[sqlx(rename = "observed_at")]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl IssPosition {
+    /// Разобрать payload источника; числа допускаются и строками.
+    /// Без корректных координат запись считается битой.
+    pub fn from_payload(payload: &Value) -> Result<Self, String> {
+        let num = |key: &str| -> Result<Option<f64>, String> {
+            let parsed = match payload.get(key) {
+                None | Some(Value::Null) => return Ok(None),
+                Some(Value::Number(n)) => n.as_f64(),
+                Some(Value::String(s)) => s.trim().parse::<f64>().ok(),
+                Some(_) => None,
+            };
+            parsed
+                .filter(|f| f.is_finite())
+                .map(Some)
+                .ok_or_else(|| format!("`{}` is not a number: {}", key, payload[key]))
+        };
+
+        if !payload.is_object() {
+            return Err("payload is not an object".to_string());
+        }
+
+        let latitude = num("latitude")?.ok_or("`latitude` is missing")?;
+        let longitude = num("longitude")?.ok_or("`longitude` is missing")?;
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(format!("coordinates out of range: {}, {}", latitude, longitude));
+        }
+
+        let timestamp = match num("timestamp")? {
+            Some(ts) => Some(
+                DateTime::from_timestamp(ts as i64, 0).ok_or_else(|| format!("`timestamp` out of range: {}", ts))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            latitude,
+            longitude,
+            altitude: num("altitude")?,
+            velocity: num("velocity")?,
+            visibility: payload.get("visibility").and_then(|v| v.as_str()).map(String::from),
+            footprint: num("footprint")?,
+            solar_lat: num("solar_lat")?,
+            solar_lon: num("solar_lon")?,
+            timestamp,
+        })
+    }
+}
+
 /// OSDR Item - данные из NASA OSDR
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct OsdrItem {
@@ -32,29 +106,1070 @@ pub struct OsdrItem {
     pub status: Option<String>,
     pub updated_at: Option<DateTime<Utc>>,
     pub inserted_at: DateTime<Utc>,
+    /// Исследование пропало из OSDR (не встретилось за полный обход)
+    pub withdrawn_at: Option<DateTime<Utc>>,
     pub raw: Value,
 }
 
+/// Нормализованные поля датасета OSDR, извлечённые из `raw`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OsdrNormalized {
+    pub organisms: Vec<String>,
+    pub assay_types: Vec<String>,
+    pub factors: Vec<String>,
+    pub mission: Option<String>,
+    pub release_date: Option<DateTime<Utc>>,
+    pub doi: Option<String>,
+    pub file_count: Option<i64>,
+}
+
+/// Карточка датасета: сохранённая запись + нормализованные поля
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrDatasetDetail {
+    pub item: OsdrItem,
+    pub normalized: OsdrNormalized,
+}
+
+/// Колонки, по которым разрешена сортировка /osdr/list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OsdrSortBy {
+    InsertedAt,
+    UpdatedAt,
+    Title,
+    DatasetId,
+}
+
+impl OsdrSortBy {
+    pub const ALLOWED: &'static str = "inserted_at, updated_at, title, dataset_id";
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "inserted_at" => Some(Self::InsertedAt),
+            "updated_at" => Some(Self::UpdatedAt),
+            "title" => Some(Self::Title),
+            "dataset_id" => Some(Self::DatasetId),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InsertedAt => "inserted_at",
+            Self::UpdatedAt => "updated_at",
+            Self::Title => "title",
+            Self::DatasetId => "dataset_id",
+        }
+    }
+}
+
+/// Фильтры и сортировка списка OSDR
+#[derive(Debug, Clone, PartialEq)]
+pub struct OsdrListFilter {
+    pub status: Option<String>,
+    pub search: Option<String>,
+    pub sort_by: OsdrSortBy,
+    pub descending: bool,
+    pub limit: i64,
+    /// Продолжить после этой позиции (keyset-пагинация)
+    pub after: Option<OsdrCursor>,
+    pub facets: OsdrFacetFilter,
+    /// Показывать ли записи, снятые с OSDR
+    pub include_withdrawn: bool,
+}
+
+/// Фасеты каталога OSDR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsdrFacet {
+    Organism,
+    ProjectType,
+    AssayTechnology,
+    ReleaseYear,
+}
+
+impl OsdrFacet {
+    pub const ALL: [Self; 4] = [Self::Organism, Self::ProjectType, Self::AssayTechnology, Self::ReleaseYear];
+
+    /// Поле `raw` (строка или массив строк); у года релиза — None, он берётся из updated_at
+    pub fn raw_key(&self) -> Option<&'static str> {
+        match self {
+            Self::Organism => Some("Organism"),
+            Self::ProjectType => Some("Project Type"),
+            Self::AssayTechnology => Some("Study Assay Technology Type"),
+            Self::ReleaseYear => None,
+        }
+    }
+}
+
+/// Значения фасетов, по которым фильтруется список (точное совпадение)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OsdrFacetFilter {
+    pub organism: Option<String>,
+    pub project_type: Option<String>,
+    pub assay_technology: Option<String>,
+    pub release_year: Option<i32>,
+}
+
+impl OsdrFacetFilter {
+    /// Значение фильтра строкового фасета
+    pub fn text(&self, facet: OsdrFacet) -> Option<&str> {
+        match facet {
+            OsdrFacet::Organism => self.organism.as_deref(),
+            OsdrFacet::ProjectType => self.project_type.as_deref(),
+            OsdrFacet::AssayTechnology => self.assay_technology.as_deref(),
+            OsdrFacet::ReleaseYear => None,
+        }
+    }
+}
+
+/// Значение фасета и число датасетов с ним
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct OsdrFacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+/// Агрегаты /osdr/facets под текущими фильтрами
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrFacets {
+    pub total: i64,
+    pub organism: Vec<OsdrFacetCount>,
+    pub project_type: Vec<OsdrFacetCount>,
+    pub assay_technology: Vec<OsdrFacetCount>,
+    pub release_year: Vec<OsdrFacetCount>,
+}
+
+/// Позиция keyset-пагинации: значение ключа сортировки и id последней отданной записи.
+/// Клиенту отдаётся непрозрачным токеном (base64url от JSON).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OsdrCursor {
+    #[serde(rename = "s")]
+    pub sort_by: OsdrSortBy,
+    #[serde(rename = "d")]
+    pub descending: bool,
+    #[serde(rename = "v")]
+    pub value: String,
+    pub id: i64,
+}
+
+impl OsdrCursor {
+    /// Курсор после item; NULL-значения кодируются так же, как их заменяет сортировка в SQL
+    pub fn after_item(item: &OsdrItem, sort_by: OsdrSortBy, descending: bool) -> Self {
+        let value = match sort_by {
+            OsdrSortBy::InsertedAt => item.inserted_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+            OsdrSortBy::UpdatedAt => item
+                .updated_at
+                .map(|t| t.to_rfc3339_opts(SecondsFormat::Micros, true))
+                .unwrap_or_else(|| "-infinity".to_string()),
+            OsdrSortBy::Title => item.title.clone().unwrap_or_default(),
+            OsdrSortBy::DatasetId => item.dataset_id.clone().unwrap_or_default(),
+        };
+        Self { sort_by, descending, value, id: item.id }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> Result<Self, String> {
+        let bytes = URL_SAFE_NO_PAD.decode(token).map_err(|_| "malformed cursor".to_string())?;
+        serde_json::from_slice(&bytes).map_err(|_| "malformed cursor".to_string())
+    }
+}
+
+/// Страница списка OSDR
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrPage {
+    pub items: Vec<OsdrItem>,
+    pub next_cursor: Option<String>,
+    /// Сколько записей подходит под фильтры (только по запросу `include_total`)
+    pub total: Option<i64>,
+}
+
+/// Строка пакетного upsert OSDR (поля уже извлечены из `raw`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct OsdrUpsertRow {
+    pub dataset_id: Option<String>,
+    pub title: Option<String>,
+    pub status: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub raw: Value,
+}
+
+/// Результат upsert записи OSDR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsdrUpsertOutcome {
+    Inserted,
+    Changed,
+    Unchanged,
+}
+
+/// Ревизия датасета OSDR; `diff` — JSON Patch (RFC 6902) от предыдущей ревизии
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OsdrItemVersion {
+    pub id: i64,
+    pub item_id: i64,
+    pub version: i32,
+    pub content_hash: String,
+    pub title: Option<String>,
+    pub status: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Value>,
+    pub diff: Option<Value>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// История изменений датасета
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrItemHistory {
+    pub dataset_id: String,
+    pub item_id: i64,
+    pub versions: Vec<OsdrItemVersion>,
+}
+
+/// Режим синхронизации OSDR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsdrSyncMode {
+    /// Пройти все страницы `hits.total`
+    Full,
+    /// Остановиться на первой странице, где все исследования уже сохранены с той же датой релиза
+    Incremental,
+}
+
+impl OsdrSyncMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "full" => Some(Self::Full),
+            "incremental" => Some(Self::Incremental),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Incremental => "incremental",
+        }
+    }
+}
+
+/// Чекпоинт постраничной синхронизации OSDR: незавершённый прогон продолжается с `next_from`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OsdrSyncCheckpoint {
+    pub mode: String,
+    pub next_from: i64,
+    pub total_hits: Option<i64>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Кто запустил синхронизацию
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncTrigger {
+    Scheduled,
+    Manual,
+}
+
+impl SyncTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Scheduled => "scheduled",
+            Self::Manual => "manual",
+        }
+    }
+}
+
+/// Ошибка сохранения отдельного исследования (образец для журнала прогонов)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OsdrSyncError {
+    pub dataset_id: Option<String>,
+    pub message: String,
+}
+
+/// Итог синхронизации OSDR
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrSyncReport {
+    /// Запись в `sync_runs`
+    pub run_id: i64,
+    pub mode: OsdrSyncMode,
+    /// С какого смещения начали (не 0 — продолжение прерванного прогона)
+    pub resumed_from: i64,
+    pub pages: u32,
+    pub fetched: usize,
+    pub inserted: usize,
+    pub changed: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+    /// Повторы внутри страницы: отброшены, не ошибка и на withdrawal не влияют
+    pub duplicates: usize,
+    /// Помечено withdrawn после полного обхода
+    pub withdrawn: usize,
+    /// Первые ошибки сохранения (не больше `OSDR_SYNC_ERROR_SAMPLES`)
+    pub error_samples: Vec<OsdrSyncError>,
+    pub total_hits: Option<i64>,
+    pub stopped_early: bool,
+    /// false — упёрлись в OSDR_MAX_PAGES, следующий прогон продолжит с чекпоинта
+    pub completed: bool,
+    /// Суммарное и максимальное время ответа OSDR API по страницам, мс
+    pub upstream_ms_total: u64,
+    pub upstream_ms_max: u64,
+}
+
+impl OsdrSyncReport {
+    pub const OSDR_SYNC_ERROR_SAMPLES: usize = 10;
+
+    pub fn new(run_id: i64, mode: OsdrSyncMode) -> Self {
+        Self {
+            run_id,
+            mode,
+            resumed_from: 0,
+            pages: 0,
+            fetched: 0,
+            inserted: 0,
+            changed: 0,
+            unchanged: 0,
+            failed: 0,
+            duplicates: 0,
+            withdrawn: 0,
+            error_samples: Vec::new(),
+            total_hits: None,
+            stopped_early: false,
+            completed: false,
+            upstream_ms_total: 0,
+            upstream_ms_max: 0,
+        }
+    }
+
+    /// Учесть ошибку сохранения; образцы копятся до лимита
+    pub fn record_failure(&mut self, dataset_id: Option<String>, message: String) {
+        self.failed += 1;
+        if self.error_samples.len() < Self::OSDR_SYNC_ERROR_SAMPLES {
+            self.error_samples.push(OsdrSyncError { dataset_id, message });
+        }
+    }
+
+    pub fn record_upstream_latency(&mut self, ms: u64) {
+        self.upstream_ms_total += ms;
+        self.upstream_ms_max = self.upstream_ms_max.max(ms);
+    }
+}
+
+/// Запись журнала прогонов синхронизации (`sync_runs`)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SyncRun {
+    pub id: i64,
+    pub source: String,
+    pub trigger: String,
+    pub mode: String,
+    /// running | succeeded | failed | interrupted
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub resumed_from: i64,
+    pub pages: i32,
+    pub fetched: i64,
+    pub inserted: i64,
+    pub changed: i64,
+    pub unchanged: i64,
+    pub failed: i64,
+    pub duplicates: i64,
+    pub withdrawn: i64,
+    pub total_hits: Option<i64>,
+    pub stopped_early: bool,
+    pub completed: bool,
+    pub upstream_ms_total: i64,
+    pub upstream_ms_max: i64,
+    pub error_samples: Value,
+    pub error: Option<String>,
+}
+
+/// Политика хранения временного ряда: все записи за `raw_days`, дальше — одна
+/// (самая новая) на интервал `bucket_secs`, старше `keep_days` — удаляются (0 — хранить всегда)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub raw_days: u32,
+    pub bucket_secs: u32,
+    pub keep_days: u32,
+}
+
+impl RetentionPolicy {
+    pub const COMPACTION_WINDOW_BUCKETS: i64 = 256;
+
+    /// Формат `raw_days:bucket_secs:keep_days`, например `7:600:90`
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split(':').map(|p| p.trim().parse::<u32>().ok());
+        let policy = Self {
+            raw_days: parts.next()??,
+            bucket_secs: parts.next()??,
+            keep_days: parts.next()??,
+        };
+        if parts.next().is_some() || policy.bucket_secs == 0 {
+            return None;
+        }
+        // Удалять раньше, чем закончится срок полного хранения, нельзя
+        if policy.keep_days != 0 && policy.keep_days < policy.raw_days {
+            return None;
+        }
+        Some(policy)
+    }
+
+    /// Границы уровней на момент `now`: до `compact_before` записи прореживаются,
+    /// до `drop_before` (если есть) — удаляются
+    pub fn cutoffs(&self, now: DateTime<Utc>) -> (DateTime<Utc>, Option<DateTime<Utc>>) {
+        let days = |d: u32| now - chrono::Duration::days(d as i64);
+        (days(self.raw_days), (self.keep_days > 0).then(|| days(self.keep_days)))
+    }
+
+    /// Окно прореживания из `COMPACTION_WINDOW_BUCKETS` интервалов, содержащее `at`;
+    /// границы кратны `bucket_secs`, поэтому интервал не делится между окнами
+    pub fn compaction_window(&self, at: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let bucket = self.bucket_secs as i64;
+        let start = at.timestamp().div_euclid(bucket) * bucket;
+        let end = start + bucket * Self::COMPACTION_WINDOW_BUCKETS;
+        let ts = |secs: i64| DateTime::<Utc>::from_timestamp(secs, 0).unwrap_or(at);
+        (ts(start), ts(end))
+    }
+}
+
+/// Таблицы временных рядов под политикой хранения
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionTable {
+    IssFetchLog,
+    SpaceCache,
+}
+
+impl RetentionTable {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::IssFetchLog => "iss_fetch_log",
+            Self::SpaceCache => "space_cache",
+        }
+    }
+}
+
+/// Итог политики хранения для одной таблицы/источника
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetentionScopeReport {
+    pub table: String,
+    /// Источник space_cache или `*` для всех спутников iss_fetch_log
+    pub scope: String,
+    pub policy: RetentionPolicy,
+    /// Удалено при прореживании
+    pub compacted: u64,
+    /// Удалено как устаревшее
+    pub dropped: u64,
+    pub batches: u32,
+}
+
+impl RetentionScopeReport {
+    pub fn new(table: RetentionTable, scope: Option<&str>, policy: RetentionPolicy) -> Self {
+        Self {
+            table: table.as_str().to_string(),
+            scope: scope.unwrap_or("*").to_string(),
+            policy,
+            compacted: 0,
+            dropped: 0,
+            batches: 0,
+        }
+    }
+}
+
+/// Прогон retention из журнала retention_runs
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RetentionRun {
+    pub id: i64,
+    pub trigger: String,
+    /// running | succeeded | failed | interrupted
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub compacted: i64,
+    pub dropped: i64,
+    pub scopes: Value,
+    pub error: Option<String>,
+}
+
+/// Текущие политики хранения и последние прогоны (/admin/retention)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionStatus {
+    pub every_seconds: u64,
+    pub batch_size: i64,
+    pub iss: RetentionPolicy,
+    pub space_default: RetentionPolicy,
+    pub space_overrides: std::collections::BTreeMap<String, RetentionPolicy>,
+    pub runs: Vec<RetentionRun>,
+}
+
 /// Space Cache - универсальный кэш космоданных
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct SpaceCache {
     pub id: i64,
     pub source: String,
+    /// Когда это содержимое впервые получено (с тех пор не менялось)
     pub fetched_at: DateTime<Utc>,
+    /// Последний запрос к источнику, вернувший то же содержимое
+    pub last_seen_at: DateTime<Utc>,
     pub payload: Value,
+    /// Payload не разобрался в типизированную модель источника (изменилась схема API)
+    pub schema_warning: Option<String>,
 }
 
 impl SpaceCache {
+    #[cfg(test)]
     pub fn new(source: String, payload: Value) -> Self {
+        let now = Utc::now();
         Self {
             id: 0,
             source,
-            fetched_at: Utc::now(),
+            fetched_at: now,
+            last_seen_at: now,
             payload,
+            schema_warning: None,
+        }
+    }
+}
+
+/// Снимок из истории space_cache: метаданные, payload — только по запросу
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SpaceSnapshot {
+    pub id: i64,
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    /// Размер payload в хранилище, байт (после сжатия TOAST)
+    pub payload_bytes: i64,
+    pub schema_warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+}
+
+/// Позиция keyset-пагинации истории: (fetched_at, id) последнего отданного снимка
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpaceHistoryCursor {
+    #[serde(rename = "t")]
+    pub fetched_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+impl SpaceHistoryCursor {
+    pub fn after(snapshot: &SpaceSnapshot) -> Self {
+        Self { fetched_at: snapshot.fetched_at, id: snapshot.id }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> Result<Self, String> {
+        let bytes = URL_SAFE_NO_PAD.decode(token).map_err(|_| "malformed cursor".to_string())?;
+        serde_json::from_slice(&bytes).map_err(|_| "malformed cursor".to_string())
+    }
+}
+
+/// Фильтр истории снимков источника (новые первыми)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceHistoryFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: i64,
+    pub after: Option<SpaceHistoryCursor>,
+    pub include_payload: bool,
+}
+
+/// Страница истории space_cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpaceHistoryPage {
+    pub source: String,
+    pub items: Vec<SpaceSnapshot>,
+    pub next_cursor: Option<String>,
+}
+
+/// Типизированный payload источника space_cache
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum SpacePayload {
+    Apod(ApodEntry),
+    Neo(NeoFeed),
+    Flares(Vec<DonkiFlare>),
+    Cme(Vec<DonkiCme>),
+    SpaceX(SpaceXLaunch),
+    Jwst(Vec<JwstImage>),
+    Tle(TleSet),
+}
+
+/// APOD - астрономическая картинка дня
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApodEntry {
+    pub date: NaiveDate,
+    pub title: String,
+    pub explanation: Option<String>,
+    /// image | video | other
+    pub media_type: String,
+    pub url: Option<String>,
+    pub hd_url: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub copyright: Option<String>,
+}
+
+impl ApodEntry {
+    pub fn from_payload(payload: &Value) -> Result<Self, String> {
+        let f = Fields::root(payload)?;
+        let date = f.str("date")?;
+        Ok(Self {
+            date: NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("`/date` is not a date: {}", date))?,
+            title: f.str("title")?,
+            explanation: f.opt_str("explanation")?,
+            media_type: f.str("media_type")?,
+            url: f.opt_str("url")?,
+            hd_url: f.opt_str("hdurl")?,
+            thumbnail_url: f.opt_str("thumbnail_url")?,
+            copyright: f.opt_str("copyright")?,
+        })
+    }
+}
+
+/// NEO feed - сближения астероидов за период, по времени сближения
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NeoFeed {
+    pub element_count: i64,
+    pub objects: Vec<NeoObject>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NeoObject {
+    pub id: String,
+    pub name: String,
+    pub approach_date: NaiveDate,
+    pub hazardous: bool,
+    pub absolute_magnitude_h: Option<f64>,
+    pub diameter_min_km: Option<f64>,
+    pub diameter_max_km: Option<f64>,
+    pub close_approach_at: Option<DateTime<Utc>>,
+    pub velocity_km_s: Option<f64>,
+    pub miss_distance_km: Option<f64>,
+    pub orbiting_body: Option<String>,
+}
+
+impl NeoFeed {
+    pub fn from_payload(payload: &Value) -> Result<Self, String> {
+        let f = Fields::root(payload)?;
+        let by_date = f.object("near_earth_objects")?.ok_or("`/near_earth_objects` is missing")?;
+
+        let mut objects = Vec::new();
+        // Ключи — даты сближения, значения — списки объектов
+        for date in by_date.value.as_object().into_iter().flat_map(|m| m.keys()) {
+            let approach_date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("`/near_earth_objects/{}` is not a date key", date))?;
+            for neo in by_date.items(date)? {
+                let diameter = neo.object("estimated_diameter")?;
+                let km = match &diameter {
+                    Some(d) => d.object("kilometers")?,
+                    None => None,
+                };
+                let approach = neo.items("close_approach_data")?.into_iter().next();
+                let (velocity, distance) = match &approach {
+                    Some(a) => (a.object("relative_velocity")?, a.object("miss_distance")?),
+                    None => (None, None),
+                };
+                objects.push(NeoObject {
+                    id: neo.str("id")?,
+                    name: neo.str("name")?,
+                    approach_date,
+                    hazardous: neo.opt_bool("is_potentially_hazardous_asteroid")?.unwrap_or(false),
+                    absolute_magnitude_h: neo.opt_num("absolute_magnitude_h")?,
+                    diameter_min_km: km.as_ref().map(|k| k.opt_num("estimated_diameter_min")).transpose()?.flatten(),
+                    diameter_max_km: km.as_ref().map(|k| k.opt_num("estimated_diameter_max")).transpose()?.flatten(),
+                    close_approach_at: approach
+                        .as_ref()
+                        .map(|a| a.opt_int("epoch_date_close_approach"))
+                        .transpose()?
+                        .flatten()
+                        .and_then(DateTime::from_timestamp_millis),
+                    velocity_km_s: velocity.as_ref().map(|v| v.opt_num("kilometers_per_second")).transpose()?.flatten(),
+                    miss_distance_km: distance.as_ref().map(|d| d.opt_num("kilometers")).transpose()?.flatten(),
+                    orbiting_body: approach.as_ref().map(|a| a.opt_str("orbiting_body")).transpose()?.flatten(),
+                });
+            }
         }
+        objects.sort_by(|a, b| (a.close_approach_at, &a.name).cmp(&(b.close_approach_at, &b.name)));
+
+        Ok(Self {
+            element_count: f.opt_int("element_count")?.unwrap_or(objects.len() as i64),
+            objects,
+        })
+    }
+}
+
+/// DONKI FLR - солнечная вспышка
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DonkiFlare {
+    pub id: String,
+    pub begin_time: DateTime<Utc>,
+    pub peak_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// Класс вспышки, например `X1.1`
+    pub class_type: Option<String>,
+    pub source_location: Option<String>,
+    pub active_region: Option<i64>,
+    pub instruments: Vec<String>,
+    pub link: Option<String>,
+}
+
+impl DonkiFlare {
+    pub fn list_from_payload(payload: &Value) -> Result<Vec<Self>, String> {
+        Fields::root_items(payload)?
+            .into_iter()
+            .map(|f| {
+                Ok(Self {
+                    id: f.str("flrID")?,
+                    begin_time: f.time("beginTime")?,
+                    peak_time: f.opt_time("peakTime")?,
+                    end_time: f.opt_time("endTime")?,
+                    class_type: f.opt_str("classType")?,
+                    source_location: f.opt_str("sourceLocation")?,
+                    active_region: f.opt_int("activeRegionNum")?,
+                    instruments: f
+                        .items("instruments")?
+                        .iter()
+                        .map(|i| i.opt_str("displayName"))
+                        .filter_map(Result::transpose)
+                        .collect::<Result<_, _>>()?,
+                    link: f.opt_str("link")?,
+                })
+            })
+            .collect()
     }
 }
 
+/// DONKI CME - корональный выброс массы с наиболее точным анализом
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DonkiCme {
+    pub id: String,
+    pub start_time: DateTime<Utc>,
+    pub source_location: Option<String>,
+    pub active_region: Option<i64>,
+    pub note: Option<String>,
+    pub link: Option<String>,
+    pub analysis: Option<CmeAnalysis>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CmeAnalysis {
+    /// Момент прохождения 21.5 солнечных радиусов
+    pub time_21_5: Option<DateTime<Utc>>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub half_angle_deg: Option<f64>,
+    pub speed_km_s: Option<f64>,
+    /// Тип по классификации SCORE: S, C, O, R, ER
+    pub kind: Option<String>,
+    pub most_accurate: bool,
+}
+
+impl DonkiCme {
+    pub fn list_from_payload(payload: &Value) -> Result<Vec<Self>, String> {
+        Fields::root_items(payload)?
+            .into_iter()
+            .map(|f| {
+                let analyses = f
+                    .items("cmeAnalyses")?
+                    .into_iter()
+                    .map(|a| {
+                        Ok(CmeAnalysis {
+                            time_21_5: a.opt_time("time21_5")?,
+                            latitude: a.opt_num("latitude")?,
+                            longitude: a.opt_num("longitude")?,
+                            half_angle_deg: a.opt_num("halfAngle")?,
+                            speed_km_s: a.opt_num("speed")?,
+                            kind: a.opt_str("type")?,
+                            most_accurate: a.opt_bool("isMostAccurate")?.unwrap_or(false),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                // Наиболее точный анализ, иначе последний
+                let analysis = match analyses.iter().position(|a| a.most_accurate) {
+                    Some(i) => analyses.into_iter().nth(i),
+                    None => analyses.into_iter().last(),
+                };
+                Ok(Self {
+                    id: f.str("activityID")?,
+                    start_time: f.time("startTime")?,
+                    source_location: f.opt_str("sourceLocation")?,
+                    active_region: f.opt_int("activeRegionNum")?,
+                    note: f.opt_str("note")?,
+                    link: f.opt_str("link")?,
+                    analysis,
+                })
+            })
+            .collect()
+    }
+}
+
+/// SpaceX - ближайший запуск
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpaceXLaunch {
+    pub id: String,
+    pub name: String,
+    pub flight_number: Option<i64>,
+    pub date_utc: DateTime<Utc>,
+    /// half | quarter | year | month | day | hour
+    pub date_precision: Option<String>,
+    pub upcoming: Option<bool>,
+    pub rocket_id: Option<String>,
+    pub launchpad_id: Option<String>,
+    pub details: Option<String>,
+    pub webcast_url: Option<String>,
+    pub patch_url: Option<String>,
+}
+
+impl SpaceXLaunch {
+    pub fn from_payload(payload: &Value) -> Result<Self, String> {
+        let f = Fields::root(payload)?;
+        let links = f.object("links")?;
+        let patch = match &links {
+            Some(l) => l.object("patch")?,
+            None => None,
+        };
+        Ok(Self {
+            id: f.str("id")?,
+            name: f.str("name")?,
+            flight_number: f.opt_int("flight_number")?,
+            date_utc: f.time("date_utc")?,
+            date_precision: f.opt_str("date_precision")?,
+            upcoming: f.opt_bool("upcoming")?,
+            rocket_id: f.opt_str("rocket")?,
+            launchpad_id: f.opt_str("launchpad")?,
+            details: f.opt_str("details")?,
+            webcast_url: links.as_ref().map(|l| l.opt_str("webcast")).transpose()?.flatten(),
+            patch_url: patch.as_ref().map(|p| p.opt_str("small")).transpose()?.flatten(),
+        })
+    }
+}
+
+/// JWST - изображение из ленты jwstapi.com
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JwstImage {
+    pub id: String,
+    pub observation_id: Option<String>,
+    pub program: Option<i64>,
+    pub mission: Option<String>,
+    pub instruments: Vec<String>,
+    pub suffix: Option<String>,
+    pub description: Option<String>,
+    pub file_type: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub url: Option<String>,
+}
+
+impl JwstImage {
+    /// Лента — массив `body` ответа API (или сам массив)
+    pub fn list_from_payload(payload: &Value) -> Result<Vec<Self>, String> {
+        let items = if payload.is_array() {
+            Fields::root_items(payload)?
+        } else {
+            Fields::root(payload)?.items("body")?
+        };
+        items
+            .into_iter()
+            .map(|f| {
+                let details = f.object("details")?;
+                let (mission, suffix, description, instruments) = match &details {
+                    Some(d) => (
+                        d.opt_str("mission")?,
+                        d.opt_str("suffix")?,
+                        d.opt_str("description")?,
+                        d.items("instruments")?
+                            .iter()
+                            .map(|i| i.opt_str("instrument"))
+                            .filter_map(Result::transpose)
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    None => (None, None, None, Vec::new()),
+                };
+                Ok(Self {
+                    id: f.str("id")?,
+                    observation_id: f.opt_str("observation_id")?,
+                    program: f.opt_int("program")?,
+                    mission,
+                    instruments,
+                    suffix,
+                    description,
+                    file_type: f.opt_str("file_type")?,
+                    thumbnail_url: f.opt_str("thumbnail")?,
+                    url: f.opt_str("location")?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// TLE - элементы орбиты (разбор строк — в `OrbitPropagator`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TleSet {
+    pub name: Option<String>,
+    pub norad_id: u64,
+    pub epoch: DateTime<Utc>,
+    pub line1: String,
+    pub line2: String,
+}
+
+/// Объект payload'а источника с JSON Pointer до него — для сообщений о расхождении схемы.
+/// null и отсутствие поля равнозначны; поле неожиданного типа — ошибка, а не None.
+struct Fields<'a> {
+    value: &'a Value,
+    path: String,
+}
+
+impl<'a> Fields<'a> {
+    fn root(value: &'a Value) -> Result<Self, String> {
+        Self::new(value, String::new())
+    }
+
+    fn new(value: &'a Value, path: String) -> Result<Self, String> {
+        if !value.is_object() {
+            return Err(format!("`{}` is not an object", if path.is_empty() { "/" } else { &path }));
+        }
+        Ok(Self { value, path })
+    }
+
+    /// Элементы корневого массива как объекты
+    fn root_items(value: &'a Value) -> Result<Vec<Self>, String> {
+        let items = value.as_array().ok_or("`/` is not an array")?;
+        items.iter().enumerate().map(|(i, v)| Self::new(v, format!("/{}", i))).collect()
+    }
+
+    fn at(&self, key: &str) -> String {
+        format!("{}/{}", self.path, key)
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        self.value.get(key).filter(|v| !v.is_null())
+    }
+
+    fn mismatch(&self, key: &str, expected: &str) -> String {
+        format!("`{}` is not {}", self.at(key), expected)
+    }
+
+    fn str(&self, key: &str) -> Result<String, String> {
+        self.opt_str(key)?.ok_or_else(|| format!("`{}` is missing", self.at(key)))
+    }
+
+    fn opt_str(&self, key: &str) -> Result<Option<String>, String> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.trim().to_string()).filter(|s| !s.is_empty())),
+            Some(_) => Err(self.mismatch(key, "a string")),
+        }
+    }
+
+    /// Число; NASA отдаёт часть чисел строками
+    fn opt_num(&self, key: &str) -> Result<Option<f64>, String> {
+        let parsed = match self.get(key) {
+            None => return Ok(None),
+            Some(Value::Number(n)) => n.as_f64(),
+            Some(Value::String(s)) => s.trim().parse::<f64>().ok(),
+            Some(_) => None,
+        };
+        parsed
+            .filter(|f| f.is_finite())
+            .map(Some)
+            .ok_or_else(|| self.mismatch(key, "a number"))
+    }
+
+    fn opt_int(&self, key: &str) -> Result<Option<i64>, String> {
+        let parsed = match self.get(key) {
+            None => return Ok(None),
+            Some(Value::Number(n)) => n.as_i64(),
+            Some(Value::String(s)) => s.trim().parse::<i64>().ok(),
+            Some(_) => None,
+        };
+        parsed.map(Some).ok_or_else(|| self.mismatch(key, "an integer"))
+    }
+
+    fn opt_bool(&self, key: &str) -> Result<Option<bool>, String> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Bool(b)) => Ok(Some(*b)),
+            Some(_) => Err(self.mismatch(key, "a boolean")),
+        }
+    }
+
+    fn time(&self, key: &str) -> Result<DateTime<Utc>, String> {
+        self.opt_time(key)?.ok_or_else(|| format!("`{}` is missing", self.at(key)))
+    }
+
+    /// RFC 3339 или формат DONKI без секунд (`2024-05-10T06:27Z`)
+    fn opt_time(&self, key: &str) -> Result<Option<DateTime<Utc>>, String> {
+        let Some(text) = self.opt_str(key)? else {
+            return Ok(None);
+        };
+        DateTime::parse_from_rfc3339(&text)
+            .map(|t| t.with_timezone(&Utc))
+            .ok()
+            .or_else(|| NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%MZ").ok().map(|t| t.and_utc()))
+            .map(Some)
+            .ok_or_else(|| self.mismatch(key, "a timestamp"))
+    }
+
+    fn object(&self, key: &str) -> Result<Option<Fields<'a>>, String> {
+        self.get(key).map(|v| Fields::new(v, self.at(key))).transpose()
+    }
+
+    /// Элементы массива-поля как объекты; отсутствующий массив — пустой
+    fn items(&self, key: &str) -> Result<Vec<Fields<'a>>, String> {
+        match self.get(key) {
+            None => Ok(Vec::new()),
+            Some(Value::Array(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| Fields::new(v, format!("{}/{}", self.at(key), i)))
+                .collect(),
+            Some(_) => Err(self.mismatch(key, "an array")),
+        }
+    }
+}
+
+/// Сводка JSON Patch: счётчики операций и строки вида `path: old → new`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JsonPatchSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub replaced: usize,
+    pub changes: Vec<String>,
+    /// В `changes` попали не все операции
+    pub truncated: bool,
+}
+
+/// Ссылка на снимок space_cache в сравнении
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpaceSnapshotRef {
+    pub id: i64,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl From<&SpaceCache> for SpaceSnapshotRef {
+    fn from(cache: &SpaceCache) -> Self {
+        Self { id: cache.id, fetched_at: cache.fetched_at }
+    }
+}
+
+/// Разница между двумя снимками источника
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpaceDiff {
+    pub source: String,
+    pub from: SpaceSnapshotRef,
+    pub to: SpaceSnapshotRef,
+    /// RFC 6902, превращает payload `from` в payload `to`
+    pub patch: Vec<Value>,
+    pub summary: JsonPatchSummary,
+}
+
 /// ISS Trend - тренд движения МКС
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IssTrend {
@@ -68,6 +1183,9 @@ pub struct IssTrend {
     pub from_lon: Option<f64>,
     pub to_lat: Option<f64>,
     pub to_lon: Option<f64>,
+    /// Статистика по всем точкам окна (None — недостаточно данных)
+    #[serde(default)]
+    pub stats: Option<IssTrendStats>,
 }
 
 impl IssTrend {
@@ -83,10 +1201,198 @@ impl IssTrend {
             from_lon: None,
             to_lat: None,
             to_lon: None,
+            stats: None,
         }
     }
 }
 
+/// ISS Trend Stats - агрегаты по окну наблюдений
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssTrendStats {
+    pub window_sec: i64,
+    pub samples: usize,
+    /// Длина пути по поверхности (без отрезков через пропуски)
+    pub ground_distance_km: f64,
+    pub avg_ground_speed_kmh: Option<f64>,
+    pub max_ground_speed_kmh: Option<f64>,
+    pub altitude_min_km: Option<f64>,
+    pub altitude_max_km: Option<f64>,
+    pub altitude_mean_km: Option<f64>,
+    /// Курс (азимут) на последнем отрезке, градусы от севера
+    pub heading_deg: Option<f64>,
+    pub gaps: Vec<IssSampleGap>,
+}
+
+/// Пропуск между соседними замерами длиннее ожидаемого интервала опроса
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssSampleGap {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub dt_sec: f64,
+}
+
+/// ISS History Point - одна точка трека МКС (представитель временного бакета)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct IssHistoryPoint {
+    pub id: i64,
+    pub fetched_at: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    pub velocity: Option<f64>,
+    pub samples: i64,
+    /// Точка рассчитана SGP4 для бакета без замеров
+    #[sqlx(default)]
+    #[serde(default)]
+    pub propagated: bool,
+    /// Освещённость станции в момент точки (если известна высота)
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub illumination: Option<Illumination>,
+}
+
+/// ISS History - трек МКС за интервал с даунсэмплингом
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssHistory {
+    pub norad_id: i32,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub step_sec: i64,
+    pub points: Vec<IssHistoryPoint>,
+    /// Входы в тень и выходы из неё по соседним реальным замерам
+    #[serde(default)]
+    pub eclipses: Vec<EclipseEvent>,
+}
+
+/// Освещённость спутника: на Солнце, в полутени или в тени Земли
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Illumination {
+    Daylight,
+    Penumbra,
+    Eclipse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EclipseEventKind {
+    Entry,
+    Exit,
+}
+
+/// Eclipse Event - вход в тень Земли или выход из неё между двумя замерами
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EclipseEvent {
+    pub kind: EclipseEventKind,
+    /// Оценка момента (линейная интерполяция границы тени)
+    pub at: DateTime<Utc>,
+    pub prev_sample_at: DateTime<Utc>,
+    pub next_sample_at: DateTime<Utc>,
+}
+
+/// ISS Illumination - освещённость последнего положения спутника
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssIllumination {
+    pub norad_id: i32,
+    pub at: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+    pub state: Illumination,
+    /// visibility из ответа wheretheiss.at — для сверки
+    pub upstream_visibility: Option<String>,
+    pub subsolar_lat: f64,
+    pub subsolar_lon: f64,
+    /// События за последние часы по сохранённым замерам
+    pub eclipses: Vec<EclipseEvent>,
+}
+
+/// Форма геозоны: круг (радиус в км) или полигон из вершин [lon, lat]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum GeofenceShape {
+    Circle { lat: f64, lon: f64, radius_km: f64 },
+    Polygon { coordinates: Vec<[f64; 2]> },
+}
+
+/// Geofence - область, о входе спутника в которую нужно сообщать
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Geofence {
+    pub id: i64,
+    pub name: String,
+    pub norad_id: i32,
+    pub shape: sqlx::types::Json<GeofenceShape>,
+    pub enabled: bool,
+    /// Был ли спутник внутри при последней проверке
+    pub inside: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Geofence Event - вход спутника в геозону или выход из неё
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct GeofenceEvent {
+    pub id: i64,
+    pub geofence_id: i64,
+    pub geofence_name: String,
+    pub norad_id: i32,
+    /// enter / exit
+    pub kind: String,
+    pub occurred_at: DateTime<Utc>,
+    pub fetch_id: Option<i64>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Orbit Position - положение спутника, рассчитанное по TLE (SGP4)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrbitPosition {
+    pub at: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Высота над эллипсоидом WGS84, км
+    pub altitude: f64,
+    /// Инерциальная скорость, км/ч
+    pub velocity: f64,
+    pub tle_epoch: DateTime<Utc>,
+}
+
+/// Точка пролёта: момент и направление на спутник с точки наблюдателя
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassPoint {
+    pub time: DateTime<Utc>,
+    pub azimuth_deg: f64,
+    pub elevation_deg: f64,
+}
+
+/// ISS Pass - пролёт МКС над наблюдателем
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssPass {
+    pub rise: PassPoint,
+    pub culmination: PassPoint,
+    pub set: PassPoint,
+    pub duration_sec: i64,
+    pub max_elevation_deg: f64,
+    /// МКС освещена Солнцем хотя бы часть пролёта
+    pub sunlit: bool,
+    /// У наблюдателя темно (Солнце ниже −6°) хотя бы часть пролёта
+    pub observer_dark: bool,
+    /// Освещённая МКС на тёмном небе — пролёт видно невооружённым глазом
+    pub visible: bool,
+}
+
+/// ISS Passes - прогноз пролётов для точки наблюдения
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssPasses {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt_m: f64,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub tle_epoch: DateTime<Utc>,
+    pub passes: Vec<IssPass>,
+}
+
 /// API Response обёртка для успешных ответов
 #[derive(Debug, Serialize)]
 pub struct ApiResponse<T: Serialize> {
@@ -0,0 +1,209 @@
+use crate::domain::{OsdrItem, OsdrNormalized};
+use crate::services::OsdrService;
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray};
+use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::io;
+use std::sync::Arc;
+
+/// Строк в одной row group Parquet (столько записей держится в памяти)
+const PARQUET_ROW_GROUP_ROWS: usize = 4096;
+
+/// Колонки CSV: запись + нормализованные поля
+const CSV_COLUMNS: [&str; 14] = [
+    "id", "dataset_id", "title", "status", "updated_at", "inserted_at", "withdrawn_at",
+    "organisms", "assay_types", "factors", "mission", "release_date", "doi", "file_count",
+];
+
+/// Формат выгрузки каталога OSDR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+            Self::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Csv => "osdr-export.csv",
+            Self::Ndjson => "osdr-export.ndjson",
+            Self::Parquet => "osdr-export.parquet",
+        }
+    }
+}
+
+/// Потоковый кодировщик: записи поступают по одной, наружу отдаются готовые куски тела ответа
+pub enum ExportEncoder {
+    Csv { header_sent: bool },
+    Ndjson,
+    Parquet { writer: Box<ArrowWriter<Vec<u8>>>, pending: Vec<OsdrItem> },
+}
+
+impl ExportEncoder {
+    pub fn new(format: ExportFormat) -> io::Result<Self> {
+        Ok(match format {
+            ExportFormat::Csv => Self::Csv { header_sent: false },
+            ExportFormat::Ndjson => Self::Ndjson,
+            ExportFormat::Parquet => {
+                let schema = parquet_batch(&[])?.schema();
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = ArrowWriter::try_new(Vec::new(), schema, Some(props)).map_err(io::Error::other)?;
+                Self::Parquet {
+                    writer: Box::new(writer),
+                    pending: Vec::with_capacity(PARQUET_ROW_GROUP_ROWS),
+                }
+            }
+        })
+    }
+
+    /// Закодировать запись; None — кусок ещё копится
+    pub fn push(&mut self, item: OsdrItem) -> io::Result<Option<Bytes>> {
+        match self {
+            Self::Csv { header_sent } => {
+                let mut out = String::new();
+                if !*header_sent {
+                    out.push_str(&csv_header());
+                    *header_sent = true;
+                }
+                out.push_str(&csv_row(&item));
+                Ok(Some(Bytes::from(out)))
+            }
+            Self::Ndjson => {
+                let mut line = serde_json::to_vec(&item).map_err(io::Error::other)?;
+                line.push(b'\n');
+                Ok(Some(Bytes::from(line)))
+            }
+            Self::Parquet { writer, pending } => {
+                pending.push(item);
+                if pending.len() < PARQUET_ROW_GROUP_ROWS {
+                    return Ok(None);
+                }
+                write_row_group(writer, pending)
+            }
+        }
+    }
+
+    /// Хвост выгрузки: заголовок пустого CSV или последняя row group и footer Parquet
+    pub fn finish(self) -> io::Result<Option<Bytes>> {
+        match self {
+            Self::Csv { header_sent: false } => Ok(Some(Bytes::from(csv_header()))),
+            Self::Csv { .. } | Self::Ndjson => Ok(None),
+            Self::Parquet { mut writer, mut pending } => {
+                let mut out = write_row_group(&mut writer, &mut pending)?
+                    .map(|b| b.to_vec())
+                    .unwrap_or_default();
+                writer.finish().map_err(io::Error::other)?;
+                writer.sync()?;
+                out.append(writer.inner_mut());
+                Ok(Some(Bytes::from(out)))
+            }
+        }
+    }
+}
+
+/// Записать накопленные строки отдельной row group и забрать её байты из буфера
+fn write_row_group(writer: &mut ArrowWriter<Vec<u8>>, pending: &mut Vec<OsdrItem>) -> io::Result<Option<Bytes>> {
+    if !pending.is_empty() {
+        let batch = parquet_batch(pending)?;
+        pending.clear();
+        writer.write(&batch).map_err(io::Error::other)?;
+        writer.flush().map_err(io::Error::other)?;
+        writer.sync()?;
+    }
+    let chunk = std::mem::take(writer.inner_mut());
+    Ok((!chunk.is_empty()).then(|| Bytes::from(chunk)))
+}
+
+/// Строки выгрузки как Arrow RecordBatch; на пустом срезе даёт схему
+fn parquet_batch(items: &[OsdrItem]) -> io::Result<RecordBatch> {
+    let normalized: Vec<OsdrNormalized> = items.iter().map(|i| OsdrService::normalize(&i.raw)).collect();
+
+    let text = |f: &dyn Fn(usize) -> Option<String>| -> ArrayRef {
+        Arc::new((0..items.len()).map(f).collect::<StringArray>())
+    };
+    let timestamps = |f: &dyn Fn(usize) -> Option<DateTime<Utc>>| -> ArrayRef {
+        let values: Vec<Option<i64>> = (0..items.len()).map(|i| f(i).map(|t| t.timestamp_micros())).collect();
+        Arc::new(TimestampMicrosecondArray::from(values).with_timezone("UTC"))
+    };
+    let lists = |f: &dyn Fn(&OsdrNormalized) -> &Vec<String>| -> ArrayRef {
+        let mut builder = ListBuilder::new(StringBuilder::new());
+        for n in &normalized {
+            for value in f(n) {
+                builder.values().append_value(value);
+            }
+            builder.append(true);
+        }
+        Arc::new(builder.finish())
+    };
+
+    RecordBatch::try_from_iter(vec![
+        ("id", Arc::new(items.iter().map(|i| i.id).collect::<Int64Array>()) as ArrayRef),
+        ("dataset_id", text(&|i| items[i].dataset_id.clone())),
+        ("title", text(&|i| items[i].title.clone())),
+        ("status", text(&|i| items[i].status.clone())),
+        ("updated_at", timestamps(&|i| items[i].updated_at)),
+        ("inserted_at", timestamps(&|i| Some(items[i].inserted_at))),
+        ("withdrawn_at", timestamps(&|i| items[i].withdrawn_at)),
+        ("organisms", lists(&|n| &n.organisms)),
+        ("assay_types", lists(&|n| &n.assay_types)),
+        ("factors", lists(&|n| &n.factors)),
+        ("mission", text(&|i| normalized[i].mission.clone())),
+        ("release_date", timestamps(&|i| normalized[i].release_date)),
+        ("doi", text(&|i| normalized[i].doi.clone())),
+        ("file_count", Arc::new(normalized.iter().map(|n| n.file_count).collect::<Int64Array>()) as ArrayRef),
+        ("raw", text(&|i| Some(items[i].raw.to_string()))),
+    ])
+    .map_err(io::Error::other)
+}
+
+fn csv_header() -> String {
+    format!("{}\r\n", CSV_COLUMNS.join(","))
+}
+
+/// Строка CSV (RFC 4180): списки нормализованных полей через `; `
+pub(crate) fn csv_row(item: &OsdrItem) -> String {
+    let n = OsdrService::normalize(&item.raw);
+    let ts = |t: Option<DateTime<Utc>>| t.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)).unwrap_or_default();
+
+    let fields = [
+        item.id.to_string(),
+        item.dataset_id.clone().unwrap_or_default(),
+        item.title.clone().unwrap_or_default(),
+        item.status.clone().unwrap_or_default(),
+        ts(item.updated_at),
+        ts(Some(item.inserted_at)),
+        ts(item.withdrawn_at),
+        n.organisms.join("; "),
+        n.assay_types.join("; "),
+        n.factors.join("; "),
+        n.mission.unwrap_or_default(),
+        ts(n.release_date),
+        n.doi.unwrap_or_default(),
+        n.file_count.map(|c| c.to_string()).unwrap_or_default(),
+    ];
+    let escaped: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+    format!("{}\r\n", escaped.join(","))
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
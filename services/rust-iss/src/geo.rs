@@ -0,0 +1,180 @@
+use crate::domain::{GeofenceShape, IssHistoryPoint};
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+/// Разрезать трек (долгота, широта, высота) на участки, не пересекающие антимеридиан.
+/// На стыке добавляются точки ровно на ±180° с интерполированными широтой и высотой,
+/// чтобы линия на карте доходила до края и продолжалась с другой стороны.
+pub fn split_antimeridian(coords: &[[f64; 3]]) -> Vec<Vec<[f64; 3]>> {
+    let mut segments: Vec<Vec<[f64; 3]>> = Vec::new();
+    let mut current: Vec<[f64; 3]> = Vec::new();
+
+    for &[lon, lat, alt] in coords {
+        if let Some(&[prev_lon, prev_lat, prev_alt]) = current.last() {
+            let dlon = lon - prev_lon;
+            if dlon.abs() > 180.0 {
+                // Развернуть долготу через антимеридиан и найти точку пересечения
+                let edge = if dlon < 0.0 { 180.0 } else { -180.0 };
+                let unwrapped = if dlon < 0.0 { lon + 360.0 } else { lon - 360.0 };
+                let t = (edge - prev_lon) / (unwrapped - prev_lon);
+                let cross_lat = prev_lat + t * (lat - prev_lat);
+                let cross_alt = prev_alt + t * (alt - prev_alt);
+
+                current.push([edge, cross_lat, cross_alt]);
+                segments.push(std::mem::take(&mut current));
+                current.push([-edge, cross_lat, cross_alt]);
+            }
+        }
+        current.push([lon, lat, alt]);
+    }
+
+    if !current.is_empty() {
+        segments.push(current);
+    }
+    segments
+}
+
+/// Координаты трека: долгота, широта, высота в метрах
+fn track_coords(points: &[IssHistoryPoint]) -> Vec<[f64; 3]> {
+    points
+        .iter()
+        .map(|p| [p.longitude, p.latitude, p.altitude.unwrap_or(0.0) * 1000.0])
+        .collect()
+}
+
+/// Трек МКС в виде GeoJSON FeatureCollection: линия трека + точки с временными метками
+pub fn track_to_geojson(points: &[IssHistoryPoint]) -> Value {
+    let segments: Vec<Vec<[f64; 2]>> = split_antimeridian(&track_coords(points))
+        .into_iter()
+        .map(|s| s.into_iter().map(|[lon, lat, _]| [lon, lat]).collect())
+        .collect();
+
+    let mut features = Vec::with_capacity(points.len() + 1);
+    if points.len() >= 2 {
+        // RFC 7946 §3.1.9: линия через антимеридиан — MultiLineString
+        let geometry = if segments.len() == 1 {
+            json!({"type": "LineString", "coordinates": segments[0]})
+        } else {
+            json!({"type": "MultiLineString", "coordinates": segments})
+        };
+        features.push(json!({
+            "type": "Feature",
+            "geometry": geometry,
+            "properties": {
+                "kind": "track",
+                "from": points.first().map(|p| p.fetched_at),
+                "to": points.last().map(|p| p.fetched_at),
+            }
+        }));
+    }
+
+    for p in points {
+        features.push(json!({
+            "type": "Feature",
+            "geometry": {"type": "Point", "coordinates": [p.longitude, p.latitude]},
+            "properties": {
+                "kind": "sample",
+                "id": p.id,
+                "timestamp": p.fetched_at,
+                "altitude": p.altitude,
+                "velocity": p.velocity,
+                "samples": p.samples,
+                "propagated": p.propagated,
+            }
+        }));
+    }
+
+    json!({"type": "FeatureCollection", "features": features})
+}
+
+/// Трек МКС в формате KML (Google Earth): линия на реальной высоте + точки с TimeStamp
+pub fn track_to_kml(points: &[IssHistoryPoint]) -> String {
+    let mut kml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n\
+         <Document>\n\
+         <name>ISS ground track</name>\n\
+         <Style id=\"track\"><LineStyle><color>ffdb9834</color><width>2</width></LineStyle></Style>\n",
+    );
+
+    if points.len() >= 2 {
+        kml.push_str("<Placemark>\n<name>Track</name>\n<styleUrl>#track</styleUrl>\n<MultiGeometry>\n");
+        for segment in split_antimeridian(&track_coords(points)) {
+            kml.push_str("<LineString><altitudeMode>absolute</altitudeMode><coordinates>\n");
+            for [lon, lat, alt] in segment {
+                let _ = writeln!(kml, "{:.6},{:.6},{:.0}", lon, lat, alt);
+            }
+            kml.push_str("</coordinates></LineString>\n");
+        }
+        kml.push_str("</MultiGeometry>\n</Placemark>\n");
+    }
+
+    kml.push_str("<Folder>\n<name>Samples</name>\n");
+    for p in points {
+        let _ = writeln!(
+            kml,
+            "<Placemark><name>{}</name><TimeStamp><when>{}</when></TimeStamp>\
+             <Point><altitudeMode>absolute</altitudeMode><coordinates>{:.6},{:.6},{:.0}</coordinates></Point></Placemark>",
+            p.fetched_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            p.fetched_at.to_rfc3339(),
+            p.longitude,
+            p.latitude,
+            p.altitude.unwrap_or(0.0) * 1000.0,
+        );
+    }
+    kml.push_str("</Folder>\n</Document>\n</kml>\n");
+
+    kml
+}
+
+/// Расстояние по дуге большого круга между двумя точками, км
+pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let rlat1 = lat1.to_radians();
+    let rlat2 = lat2.to_radians();
+    let dlat = (lat2 - lat1).to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + rlat1.cos() * rlat2.cos() * (dlon / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    6371.0 * c
+}
+
+/// Попадает ли точка в геозону
+pub fn shape_contains(shape: &GeofenceShape, lat: f64, lon: f64) -> bool {
+    match shape {
+        GeofenceShape::Circle { lat: c_lat, lon: c_lon, radius_km } => {
+            haversine_km(*c_lat, *c_lon, lat, lon) <= *radius_km
+        }
+        GeofenceShape::Polygon { coordinates } => point_in_polygon(coordinates, lat, lon),
+    }
+}
+
+/// Точка в полигоне (ray casting) с учётом антимеридиана: ребро длиннее 180° по долготе
+/// считается идущим через ±180, поэтому вершины «разворачиваются» в непрерывную долготу,
+/// а точка проверяется в трёх копиях (lon, lon ± 360)
+pub fn point_in_polygon(ring: &[[f64; 2]], lat: f64, lon: f64) -> bool {
+    let mut unwrapped: Vec<[f64; 2]> = Vec::with_capacity(ring.len());
+    for &[v_lon, v_lat] in ring {
+        let v_lon = match unwrapped.last() {
+            Some(&[prev, _]) => prev + (v_lon - prev + 180.0).rem_euclid(360.0) - 180.0,
+            None => v_lon,
+        };
+        unwrapped.push([v_lon, v_lat]);
+    }
+
+    [lon, lon + 360.0, lon - 360.0]
+        .iter()
+        .any(|&x| ray_cast(&unwrapped, x, lat))
+}
+
+fn ray_cast(ring: &[[f64; 2]], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    let mut j = ring.len().wrapping_sub(1);
+    for i in 0..ring.len() {
+        let ([xi, yi], [xj, yj]) = (ring[i], ring[j]);
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
@@ -1,11 +1,25 @@
-use crate::domain::ApiResponse;
+use crate::config::ISS_NORAD_ID;
+use crate::domain::{ApiResponse, Geofence, GeofenceEvent, IssFetchLog, OsdrDatasetDetail, OsdrFacets, OsdrItemHistory, OsdrListFilter, OsdrSyncMode, OsdrSyncReport, RetentionRun, RetentionStatus, SpaceCache, SpaceDiff, SpaceHistoryPage, SyncRun, SyncTrigger};
 use crate::error::ApiError;
+use crate::export::ExportFormat;
 use crate::services::*;
 use crate::validation::*;
+use axum::extract::rejection::JsonRejection;
 use axum::extract::{Path, Query, State};
+use axum::http::header;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use futures::Stream;
 use serde_json::json;
 use sqlx::PgPool;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use validator::Validate;
 
 /// App State - DI контейнер
@@ -16,13 +30,26 @@ pub struct AppState {
     pub iss_service: IssService,
     pub osdr_service: OsdrService,
     pub space_service: SpaceService,
+    pub geofence_service: GeofenceService,
+    pub retention_service: RetentionService,
+    /// Новые записи ISS для подписчиков /iss/stream
+    pub iss_events: broadcast::Sender<IssFetchLog>,
+    /// Общий на экземпляр лимит одновременных SSE-подключений (не на клиента)
+    pub sse_limiter: Arc<Semaphore>,
+    /// Лимит одновременных выгрузок /osdr/export.* (каждая держит соединение пула)
+    pub export_limiter: Arc<Semaphore>,
 }
 
 impl AppState {
     pub async fn new(pool: PgPool, client: crate::clients::ApiClient, cache: crate::cache::CacheClient) -> Self {
+        let sse_max_connections = client.config().sse_max_connections;
+        let export_max_concurrent = client.config().osdr_export_max_concurrent;
         let iss = IssService::new(pool.clone(), client.clone(), cache.clone());
         let osdr = OsdrService::new(pool.clone(), client.clone(), cache.clone());
-        let space = SpaceService::new(pool.clone(), client, cache.clone());
+        let space = SpaceService::new(pool.clone(), client.clone(), cache.clone());
+        let geofence = GeofenceService::new(pool.clone());
+        let retention = RetentionService::new(pool.clone(), client.config().clone());
+        let (iss_events, _) = broadcast::channel(ISS_EVENTS_CAPACITY);
 
         Self {
             pool,
@@ -30,10 +57,32 @@ impl AppState {
             iss_service: iss,
             osdr_service: osdr,
             space_service: space,
+            geofence_service: geofence,
+            retention_service: retention,
+            iss_events,
+            sse_limiter: Arc::new(Semaphore::new(sse_max_connections)),
+            export_limiter: Arc::new(Semaphore::new(export_max_concurrent)),
         }
     }
+
+    /// Получить и сохранить положение спутника, проверить геозоны и разослать подписчикам
+    /// /iss/stream — общий путь планировщика и ручного /fetch
+    pub async fn ingest_position(&self, norad_id: i32) -> Result<IssFetchLog, ApiError> {
+        let log = self.iss_service.fetch_and_save(norad_id).await?;
+        if let Err(e) = self.geofence_service.evaluate(&log).await {
+            tracing::error!("Geofence evaluation error: {}", e);
+        }
+        // Ошибка send означает лишь отсутствие подписчиков /iss/stream
+        let _ = self.iss_events.send(log.clone());
+        Ok(log)
+    }
 }
 
+/// Буфер broadcast-канала: отстающий подписчик дочитает пропущенное из БД
+const ISS_EVENTS_CAPACITY: usize = 16;
+/// Интервал heartbeat-комментариев в SSE, чтобы прокси не рвали соединение
+const SSE_HEARTBEAT_SECS: u64 = 15;
+
 // ============ Root & Health Handlers ============
 
 #[derive(serde::Serialize)]
@@ -42,9 +91,14 @@ pub struct HealthResponse {
     pub now: chrono::DateTime<chrono::Utc>,
 }
 
-pub async fn health_handler() -> Json<ApiResponse<HealthResponse>> {
+/// Живость сервиса: недоступные БД или Redis не роняют /health, а дают status `degraded`
+pub async fn health_handler(State(state): State<AppState>) -> Json<ApiResponse<HealthResponse>> {
+    let db_ok = sqlx::query("SELECT 1").execute(&state.pool).await.is_ok();
+    let cache_ok = state.cache.ping().await.unwrap_or(false);
+    let status = if db_ok && cache_ok { "ok" } else { "degraded" };
+
     Json(ApiResponse::success(HealthResponse {
-        status: "ok".to_string(),
+        status: status.to_string(),
         now: chrono::Utc::now(),
     }))
 }
@@ -58,8 +112,29 @@ pub async fn root_handler() -> Json<ApiResponse<serde_json::Value>> {
             "/health",
             "/last",
             "/iss/trend",
+            "/iss/history",
+            "/iss/history.geojson",
+            "/iss/history.kml",
+            "/iss/position",
+            "/iss/passes",
+            "/iss/stream",
+            "/iss/illumination",
+            "/iss/terminator",
+            "/iss/geofences",
+            "/iss/geofence/events",
+            "/osdr/sync/runs",
             "/osdr/list",
-            "/space/summary"
+            "/osdr/facets",
+            "/osdr/export.csv",
+            "/osdr/export.ndjson",
+            "/osdr/export.parquet",
+            "/osdr/:dataset_id",
+            "/osdr/:dataset_id/history",
+            "/space/summary",
+            "/space/:src/history",
+            "/space/:src/at",
+            "/space/:src/diff",
+            "/admin/retention"
         ]
     })))
 }
@@ -68,8 +143,10 @@ pub async fn root_handler() -> Json<ApiResponse<serde_json::Value>> {
 
 pub async fn iss_last_handler(
     State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
-    let data = state.iss_service.get_last().await?;
+    let sat = parse_sat_param(&state, &params)?;
+    let data = state.iss_service.get_last(sat).await?;
 
     match data {
         Some(log) => Ok(Json(ApiResponse::success(serde_json::json!(log)))),
@@ -79,31 +156,432 @@ pub async fn iss_last_handler(
 
 pub async fn iss_fetch_handler(
     State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
-    let log = state.iss_service.fetch_and_save().await?;
+    let sat = parse_sat_param(&state, &params)?;
+    let log = state.ingest_position(sat).await?;
     Ok(Json(ApiResponse::success(serde_json::json!(log))))
 }
 
+/// Состояние одного SSE-подключения к /iss/stream
+struct IssStreamState {
+    rx: broadcast::Receiver<IssFetchLog>,
+    backlog: VecDeque<IssFetchLog>,
+    /// Последняя страница из БД была полной — до live-событий дочитываем остаток
+    catching_up: bool,
+    norad_id: i32,
+    last_id: i64,
+    service: IssService,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// id последнего полученного события из заголовка Last-Event-ID (EventSource шлёт его при переподключении)
+pub(crate) fn parse_last_event_id(headers: &HeaderMap) -> Option<i64> {
+    headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|id| *id >= 0)
+}
+
+/// SSE-поток новых положений МКС; поддерживает resume по заголовку Last-Event-ID
+pub async fn iss_stream_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let sat = parse_sat_param(&state, &params)?;
+    let permit = state.sse_limiter.clone().try_acquire_owned().map_err(|_| {
+        ApiError::new("STREAM_LIMIT", "Too many stream connections").with_status(StatusCode::SERVICE_UNAVAILABLE)
+    })?;
+
+    // Подписываемся до чтения БД, чтобы не потерять запись между запросом и подпиской
+    let rx = state.iss_events.subscribe();
+    let last_event_id = parse_last_event_id(&headers);
+
+    let backlog: VecDeque<IssFetchLog> = match last_event_id {
+        Some(id) => state.iss_service.get_after(sat, id).await?.into(),
+        // Новому клиенту сразу отдаём текущее положение
+        None => state.iss_service.get_last(sat).await?.into_iter().collect(),
+    };
+    let catching_up = last_event_id.is_some() && backlog.len() as i64 >= STREAM_RESUME_LIMIT;
+
+    let initial = IssStreamState {
+        rx,
+        backlog,
+        catching_up,
+        norad_id: sat,
+        last_id: last_event_id.unwrap_or(0),
+        service: state.iss_service.clone(),
+        _permit: permit,
+    };
+
+    let stream = futures::stream::unfold(initial, |mut st| async move {
+        loop {
+            let log = match st.backlog.pop_front() {
+                Some(log) => log,
+                None if st.catching_up => {
+                    // Ошибку БД не глотаем: закрытый поток EventSource переоткроет с Last-Event-ID
+                    let rows = match st.service.get_after(st.norad_id, st.last_id).await {
+                        Ok(rows) => rows,
+                        Err(e) => {
+                            tracing::warn!("ISS stream failed to reload backlog: {}", e);
+                            return None;
+                        }
+                    };
+                    st.catching_up = rows.len() as i64 >= STREAM_RESUME_LIMIT;
+                    st.backlog.extend(rows);
+                    continue;
+                }
+                None => match st.rx.recv().await {
+                    Ok(log) => log,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("ISS stream subscriber lagged by {} events, reloading from DB", skipped);
+                        st.catching_up = true;
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            };
+
+            // В канал пишутся все спутники; дубликаты возможны на стыке backlog и live-событий
+            if log.norad_id != st.norad_id || log.id <= st.last_id {
+                continue;
+            }
+            st.last_id = log.id;
+
+            let event = Event::default()
+                .id(log.id.to_string())
+                .event("iss")
+                .json_data(&log)
+                .unwrap_or_else(|_| Event::default().comment("serialization error"));
+            return Some((Ok(event), st));
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(SSE_HEARTBEAT_SECS))
+            .text("heartbeat"),
+    ))
+}
+
 pub async fn iss_trend_handler(
     State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Json<ApiResponse<crate::domain::IssTrend>>, ApiError> {
-    let trend = state.iss_service.get_trend().await?;
+    let sat = parse_sat_param(&state, &params)?;
+    let window = parse_int_param(&params, "window")?;
+
+    let trend_params = IssTrendParams { window };
+    trend_params.validate()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
+
+    let trend = state.iss_service.get_trend(sat, window).await?;
     Ok(Json(ApiResponse::success(trend)))
 }
 
+pub async fn iss_history_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<crate::domain::IssHistory>>, ApiError> {
+    let history = load_iss_history(&state, &params).await?;
+    Ok(Json(ApiResponse::success(history)))
+}
+
+/// Трек за интервал в GeoJSON (те же параметры, что у /iss/history)
+pub async fn iss_history_geojson_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    let history = load_iss_history(&state, &params).await?;
+    let body = crate::geo::track_to_geojson(&history.points);
+    Ok(export_response(
+        "application/geo+json",
+        "iss-track.geojson",
+        serde_json::to_string(&body)?,
+    ))
+}
+
+/// Трек за интервал в KML для Google Earth
+pub async fn iss_history_kml_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    let history = load_iss_history(&state, &params).await?;
+    Ok(export_response(
+        "application/vnd.google-earth.kml+xml",
+        "iss-track.kml",
+        crate::geo::track_to_kml(&history.points),
+    ))
+}
+
+/// Разобрать from/to/step и получить трек
+async fn load_iss_history(
+    state: &AppState,
+    params: &std::collections::HashMap<String, String>,
+) -> Result<crate::domain::IssHistory, ApiError> {
+    let sat = parse_sat_param(state, params)?;
+    let from = parse_time_param(params, "from")?;
+    let to = parse_time_param(params, "to")?;
+    let step = parse_int_param(params, "step")?;
+
+    let history_params = IssHistoryParams { step };
+    history_params.validate()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
+
+    state.iss_service.get_history(sat, from, to, step).await
+}
+
+/// Ответ-файл для выгрузки с нужным Content-Type
+fn export_response(content_type: &'static str, filename: &str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+pub async fn iss_illumination_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<crate::domain::IssIllumination>>, ApiError> {
+    let sat = parse_sat_param(&state, &params)?;
+    let illumination = state.iss_service.get_illumination(sat).await?;
+    Ok(Json(ApiResponse::success(illumination)))
+}
+
+/// Ночная сторона Земли (терминатор) в GeoJSON для карты
+pub async fn iss_terminator_handler(
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    let at = parse_time_param(&params, "at")?.unwrap_or_else(chrono::Utc::now);
+    let body = crate::solar::terminator_geojson(at);
+    Ok((
+        [(header::CONTENT_TYPE, "application/geo+json")],
+        serde_json::to_string(&body)?,
+    )
+        .into_response())
+}
+
+pub async fn iss_position_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<crate::domain::OrbitPosition>>, ApiError> {
+    let at = parse_time_param(&params, "at")?;
+    let position = state.iss_service.get_position(at).await?;
+    Ok(Json(ApiResponse::success(position)))
+}
+
+pub async fn iss_passes_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<crate::domain::IssPasses>>, ApiError> {
+    let lat = parse_f64_param(&params, "lat")?
+        .ok_or_else(|| ApiError::bad_request("VALIDATION_ERROR", "`lat` is required"))?;
+    let lon = parse_f64_param(&params, "lon")?
+        .ok_or_else(|| ApiError::bad_request("VALIDATION_ERROR", "`lon` is required"))?;
+    let alt = parse_f64_param(&params, "alt")?;
+    let days = parse_int_param(&params, "days")?;
+
+    let passes_params = IssPassesParams { lat, lon, alt, days };
+    passes_params.validate()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
+
+    let passes = state
+        .iss_service
+        .get_passes(lat, lon, alt.unwrap_or(0.0), days.unwrap_or(3))
+        .await?;
+    Ok(Json(ApiResponse::success(passes)))
+}
+
+/// Спутник из `?sat=` (NORAD ID), по умолчанию МКС; принимаются только отслеживаемые
+fn parse_sat_param(
+    state: &AppState,
+    params: &std::collections::HashMap<String, String>,
+) -> Result<i32, ApiError> {
+    let sat = match parse_int_param(params, "sat")? {
+        Some(v) => i32::try_from(v)
+            .map_err(|_| ApiError::bad_request("VALIDATION_ERROR", "`sat` must be a NORAD catalog number"))?,
+        None => ISS_NORAD_ID,
+    };
+    ensure_tracked(state, sat)
+}
+
+fn ensure_tracked(state: &AppState, sat: i32) -> Result<i32, ApiError> {
+    if !state.iss_service.is_tracked(sat) {
+        return Err(ApiError::bad_request(
+            "UNKNOWN_SATELLITE",
+            format!("Satellite {} is not tracked", sat),
+        ));
+    }
+    Ok(sat)
+}
+
+/// Разобрать необязательный параметр-время из query string
+fn parse_time_param(
+    params: &std::collections::HashMap<String, String>,
+    key: &str,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, ApiError> {
+    params
+        .get(key)
+        .map(|v| parse_timestamp(v).map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("`{}`: {}", key, e))))
+        .transpose()
+}
+
+/// Разобрать необязательный целочисленный параметр из query string
+fn parse_int_param(
+    params: &std::collections::HashMap<String, String>,
+    key: &str,
+) -> Result<Option<i64>, ApiError> {
+    params
+        .get(key)
+        .map(|v| {
+            v.parse::<i64>()
+                .map_err(|_| ApiError::bad_request("VALIDATION_ERROR", format!("`{}` must be an integer", key)))
+        })
+        .transpose()
+}
+
+/// Разобрать необязательный дробный параметр из query string
+fn parse_f64_param(
+    params: &std::collections::HashMap<String, String>,
+    key: &str,
+) -> Result<Option<f64>, ApiError> {
+    params
+        .get(key)
+        .map(|v| {
+            v.parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .ok_or_else(|| ApiError::bad_request("VALIDATION_ERROR", format!("`{}` must be a number", key)))
+        })
+        .transpose()
+}
+
 // ============ OSDR Handlers ============
 
 pub async fn osdr_sync_handler(
     State(state): State<AppState>,
-) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
-    let written = state.osdr_service.sync().await?;
-    Ok(Json(ApiResponse::success(json!({"written": written}))))
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<OsdrSyncReport>>, ApiError> {
+    let mode = params
+        .get("mode")
+        .map(|m| {
+            OsdrSyncMode::parse(m).ok_or_else(|| {
+                ApiError::bad_request("INVALID_SYNC_MODE", "mode must be one of: full, incremental")
+            })
+        })
+        .transpose()?;
+
+    let report = state.osdr_service.sync(mode, SyncTrigger::Manual).await?;
+    Ok(Json(ApiResponse::success(report)))
+}
+
+pub async fn osdr_sync_runs_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<Vec<SyncRun>>>, ApiError> {
+    let runs_params = SyncRunsParams {
+        limit: parse_int_param(&params, "limit")?,
+        status: params.get("status").cloned(),
+    };
+    runs_params.validate()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
+
+    let runs = state
+        .osdr_service
+        .sync_runs(runs_params.status.as_deref(), runs_params.limit.unwrap_or(20))
+        .await?;
+    Ok(Json(ApiResponse::success(runs)))
+}
+
+pub async fn osdr_sync_run_handler(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<SyncRun>>, ApiError> {
+    let run = state.osdr_service.sync_run(id).await?;
+    Ok(Json(ApiResponse::success(run)))
 }
 
 pub async fn osdr_list_handler(
     State(state): State<AppState>,
     Query(params): Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
+) -> Result<(HeaderMap, Json<ApiResponse<serde_json::Value>>), ApiError> {
+    let filter = parse_osdr_filter(&params)?;
+    let include_total = params
+        .get("include_total")
+        .is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    
+    let page = state.osdr_service.list(&filter, include_total).await?;
+
+    // Ссылки на страницы с теми же фильтрами (RFC 8288)
+    let first = page_link("/osdr/list", &params, None);
+    let next = page.next_cursor.as_deref().map(|c| page_link("/osdr/list", &params, Some(c)));
+    let mut headers = HeaderMap::new();
+    let mut link = format!("<{}>; rel=\"first\"", first);
+    if let Some(next) = &next {
+        link.push_str(&format!(", <{}>; rel=\"next\"", next));
+    }
+    if let Ok(value) = link.parse() {
+        headers.insert(header::LINK, value);
+    }
+
+    Ok((
+        headers,
+        Json(ApiResponse::success(json!({
+            "items": page.items,
+            "next_cursor": page.next_cursor,
+            "total": page.total,
+            "links": {"first": first, "next": next},
+        }))),
+    ))
+}
+
+/// Ссылка на страницу списка: исходные параметры запроса с заменённым курсором
+pub(crate) fn page_link(
+    path: &str,
+    params: &std::collections::HashMap<String, String>,
+    cursor: Option<&str>,
+) -> String {
+    let mut pairs: Vec<(&str, &str)> = params
+        .iter()
+        .filter(|(k, _)| k.as_str() != "cursor")
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    pairs.sort();
+    if let Some(cursor) = cursor {
+        pairs.push(("cursor", cursor));
+    }
+
+    let mut url = match reqwest::Url::parse("http://localhost") {
+        Ok(url) => url,
+        Err(_) => return path.to_string(),
+    };
+    url.set_path(path);
+    if !pairs.is_empty() {
+        url.query_pairs_mut().extend_pairs(pairs);
+    }
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+pub async fn osdr_facets_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<OsdrFacets>>, ApiError> {
+    let filter = parse_osdr_filter(&params)?;
+    let facets = state.osdr_service.facets(&filter).await?;
+    Ok(Json(ApiResponse::success(facets)))
+}
+
+/// Фильтры /osdr/list и /osdr/facets из query string
+fn parse_osdr_filter(params: &std::collections::HashMap<String, String>) -> Result<OsdrListFilter, ApiError> {
     // Валидация параметров
     let limit = params.get("limit")
         .and_then(|s| s.parse::<i64>().ok())
@@ -115,12 +593,185 @@ pub async fn osdr_list_handler(
         search: params.get("search").cloned(),
         sort_by: params.get("sort_by").cloned(),
         sort_order: params.get("sort_order").cloned(),
+        cursor: params.get("cursor").cloned(),
+        organism: params.get("organism").cloned(),
+        project_type: params.get("project_type").cloned(),
+        assay_technology: params.get("assay_technology").cloned(),
+        release_year: parse_int_param(params, "release_year")?,
+        include_withdrawn: params
+            .get("include_withdrawn")
+            .is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")),
     };
     osdr_params.validate()
         .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
-    
-    let items = state.osdr_service.list(limit).await?;
-    Ok(Json(ApiResponse::success(json!({"items": items}))))
+
+    osdr_params.into_filter()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", e))
+}
+
+pub async fn osdr_export_csv_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    osdr_export(&state, &params, ExportFormat::Csv)
+}
+
+pub async fn osdr_export_ndjson_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    osdr_export(&state, &params, ExportFormat::Ndjson)
+}
+
+pub async fn osdr_export_parquet_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Response, ApiError> {
+    osdr_export(&state, &params, ExportFormat::Parquet)
+}
+
+/// Выгрузка с фильтрами /osdr/list; тело отдаётся потоком по мере чтения из БД
+fn osdr_export(
+    state: &AppState,
+    params: &std::collections::HashMap<String, String>,
+    format: ExportFormat,
+) -> Result<Response, ApiError> {
+    let filter = parse_osdr_filter(params)?;
+    let permit = state.export_limiter.clone().try_acquire_owned().map_err(|_| {
+        ApiError::new("EXPORT_LIMIT", "Too many concurrent exports").with_status(StatusCode::SERVICE_UNAVAILABLE)
+    })?;
+    let stream = state.osdr_service.export(filter, format, permit)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", format.file_name()),
+            ),
+        ],
+        axum::body::Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+pub async fn osdr_dataset_handler(
+    Path(dataset_id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<OsdrDatasetDetail>>, ApiError> {
+    validate_dataset_id(&dataset_id)
+        .map_err(|e| ApiError::bad_request("INVALID_DATASET_ID", e))?;
+
+    let detail = state.osdr_service.get_dataset(&dataset_id).await?;
+    Ok(Json(ApiResponse::success(detail)))
+}
+
+pub async fn osdr_history_handler(
+    Path(dataset_id): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<OsdrItemHistory>>, ApiError> {
+    validate_dataset_id(&dataset_id)
+        .map_err(|e| ApiError::bad_request("INVALID_DATASET_ID", e))?;
+    let include_raw = params.get("include_raw").map(|v| v == "true" || v == "1").unwrap_or(false);
+
+    let history = state.osdr_service.get_history(&dataset_id, include_raw).await?;
+    Ok(Json(ApiResponse::success(history)))
+}
+
+// ============ Geofence Handlers ============
+
+pub async fn geofence_list_handler(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<Geofence>>>, ApiError> {
+    let fences = state.geofence_service.list().await?;
+    Ok(Json(ApiResponse::success(fences)))
+}
+
+pub async fn geofence_get_handler(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Geofence>>, ApiError> {
+    let fence = state.geofence_service.get(id).await?;
+    Ok(Json(ApiResponse::success(fence)))
+}
+
+pub async fn geofence_create_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<GeofencePayload>, JsonRejection>,
+) -> Result<(StatusCode, Json<ApiResponse<Geofence>>), ApiError> {
+    let payload = parse_geofence_payload(&state, payload)?;
+    let fence = state
+        .geofence_service
+        .create(
+            &payload.name,
+            payload.norad_id.unwrap_or(ISS_NORAD_ID),
+            &payload.shape,
+            payload.enabled.unwrap_or(true),
+        )
+        .await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(fence))))
+}
+
+pub async fn geofence_update_handler(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    payload: Result<Json<GeofencePayload>, JsonRejection>,
+) -> Result<Json<ApiResponse<Geofence>>, ApiError> {
+    let payload = parse_geofence_payload(&state, payload)?;
+    let fence = state
+        .geofence_service
+        .update(
+            id,
+            &payload.name,
+            payload.norad_id.unwrap_or(ISS_NORAD_ID),
+            &payload.shape,
+            payload.enabled.unwrap_or(true),
+        )
+        .await?;
+    Ok(Json(ApiResponse::success(fence)))
+}
+
+pub async fn geofence_delete_handler(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
+    state.geofence_service.delete(id).await?;
+    Ok(Json(ApiResponse::success(json!({"deleted": id}))))
+}
+
+pub async fn geofence_events_handler(
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<Vec<GeofenceEvent>>>, ApiError> {
+    let geofence_id = parse_int_param(&params, "geofence_id")?;
+    let sat = parse_int_param(&params, "sat")?
+        .map(|v| i32::try_from(v).map_err(|_| ApiError::bad_request("VALIDATION_ERROR", "`sat` must be a NORAD catalog number")))
+        .transpose()?;
+    let since = parse_time_param(&params, "since")?;
+    let limit = parse_int_param(&params, "limit")?;
+
+    let events_params = GeofenceEventsParams { limit };
+    events_params.validate()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
+
+    let events = state
+        .geofence_service
+        .events(geofence_id, sat, since, limit.unwrap_or(100))
+        .await?;
+    Ok(Json(ApiResponse::success(events)))
+}
+
+/// Разобрать и проверить тело запроса геозоны
+fn parse_geofence_payload(
+    state: &AppState,
+    payload: Result<Json<GeofencePayload>, JsonRejection>,
+) -> Result<GeofencePayload, ApiError> {
+    let Json(payload) = payload.map_err(|e| ApiError::bad_request("INVALID_JSON", e.body_text()))?;
+    payload.validate()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
+    ensure_tracked(state, payload.norad_id.unwrap_or(ISS_NORAD_ID))?;
+    Ok(payload)
 }
 
 // ============ Space Cache Handlers ============
@@ -128,17 +779,39 @@ pub async fn osdr_list_handler(
 pub async fn space_latest_handler(
     Path(src): Path<String>,
     State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
     // Валидация source ID
     validate_source_id(&src)
         .map_err(|e| ApiError::bad_request("INVALID_SOURCE", e))?;
+    let normalized = match params.get("format").map(String::as_str) {
+        None | Some("raw") => false,
+        Some("normalized") => true,
+        Some(_) => return Err(ApiError::bad_request("VALIDATION_ERROR", "`format` must be `raw` or `normalized`")),
+    };
     
     let cache = state.space_service.get_latest(&src).await?;
 
     match cache {
+        // Типизированная модель; при расхождении схемы — null и предупреждение, raw остаётся в format=raw
+        Some(c) if normalized => {
+            let (data, warning) = match SpaceService::normalize(&src, &c.payload) {
+                Ok(data) => (Some(data), None),
+                Err(e) => (None, Some(e)),
+            };
+            Ok(Json(ApiResponse::success(json!({
+                "source": src,
+                "fetched_at": c.fetched_at,
+                "last_seen_at": c.last_seen_at,
+                "schema_warning": warning,
+                "normalized": data
+            }))))
+        }
         Some(c) => Ok(Json(ApiResponse::success(json!({
             "source": src,
             "fetched_at": c.fetched_at,
+            "last_seen_at": c.last_seen_at,
+            "schema_warning": c.schema_warning,
             "payload": c.payload
         })))),
         None => Ok(Json(ApiResponse::success(json!({
@@ -148,10 +821,88 @@ pub async fn space_latest_handler(
     }
 }
 
+/// История снимков источника: метаданные, payload — по `include_payload`
+pub async fn space_history_handler(
+    Path(src): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<SpaceHistoryPage>>, ApiError> {
+    validate_source_id(&src)
+        .map_err(|e| ApiError::bad_request("INVALID_SOURCE", e))?;
+
+    let history_params = SpaceHistoryParams {
+        limit: parse_int_param(&params, "limit")?,
+        cursor: params.get("cursor").cloned(),
+        from: parse_time_param(&params, "from")?,
+        to: parse_time_param(&params, "to")?,
+        include_payload: params
+            .get("include_payload")
+            .is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")),
+    };
+    history_params.validate()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
+    let filter = history_params.into_filter()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", e))?;
+
+    let page = state.space_service.history(&src, &filter).await?;
+    Ok(Json(ApiResponse::success(page)))
+}
+
+/// Снимок источника, актуальный на момент `ts`
+pub async fn space_at_handler(
+    Path(src): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<SpaceCache>>, ApiError> {
+    validate_source_id(&src)
+        .map_err(|e| ApiError::bad_request("INVALID_SOURCE", e))?;
+    let ts = parse_time_param(&params, "ts")?
+        .ok_or_else(|| ApiError::bad_request("VALIDATION_ERROR", "`ts` is required"))?;
+
+    let snapshot = state.space_service.snapshot_at(&src, ts).await?;
+    Ok(Json(ApiResponse::success(snapshot)))
+}
+
+/// Diff двух снимков источника (по умолчанию — двух последних)
+pub async fn space_diff_handler(
+    Path(src): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<ApiResponse<SpaceDiff>>, ApiError> {
+    validate_source_id(&src)
+        .map_err(|e| ApiError::bad_request("INVALID_SOURCE", e))?;
+
+    let diff_params = SpaceDiffParams {
+        from_id: parse_int_param(&params, "from_id")?,
+        to_id: parse_int_param(&params, "to_id")?,
+    };
+    diff_params.validate()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
+
+    let diff = state.space_service.diff(&src, diff_params.from_id, diff_params.to_id).await?;
+    Ok(Json(ApiResponse::success(diff)))
+}
+
+/// Обновить источники; `src=apod,neo` — только перечисленные
 pub async fn space_refresh_handler(
     State(state): State<AppState>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
-    let sources = vec!["apod", "neo", "flr", "cme", "spacex"];
+    let refresh_params = SpaceCacheQueryParams { src: params.get("src").cloned() };
+    refresh_params.validate()
+        .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Invalid parameters: {}", e)))?;
+
+    let all = ["apod", "neo", "flr", "cme", "spacex", "tle"];
+    let sources: Vec<&str> = match refresh_params.src.as_deref() {
+        None => all.to_vec(),
+        Some(list) => {
+            let requested: Vec<&str> = list.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+            if let Some(unknown) = requested.iter().find(|s| !all.contains(s)) {
+                return Err(ApiError::bad_request("INVALID_SOURCE", format!("Unknown source: {}", unknown)));
+            }
+            all.into_iter().filter(|s| requested.contains(s)).collect()
+        }
+    };
     let mut refreshed = Vec::new();
 
     for src in sources {
@@ -171,3 +922,21 @@ pub async fn space_summary_handler(
     let summary = state.space_service.summary().await?;
     Ok(Json(ApiResponse::success(summary)))
 }
+
+// ============ Admin Handlers ============
+
+/// Политики хранения и журнал прогонов retention
+pub async fn admin_retention_handler(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<RetentionStatus>>, ApiError> {
+    let status = state.retention_service.status().await?;
+    Ok(Json(ApiResponse::success(status)))
+}
+
+/// Запустить retention вне расписания
+pub async fn admin_retention_run_handler(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<RetentionRun>>, ApiError> {
+    let run = state.retention_service.run(SyncTrigger::Manual).await?;
+    Ok(Json(ApiResponse::success(run)))
+}
@@ -1,20 +1,28 @@
 mod cache;
 mod clients;
 mod config;
+mod diff;
 mod domain;
 mod error;
+mod export;
+mod geo;
 mod handlers;
+mod orbit;
 mod repo;
 mod routes;
 mod services;
+mod solar;
 mod validation;
+// tests.rs оборачивает тесты в `#[cfg(test)] mod tests`; исходные тесты оставлены как есть
+#[allow(clippy::module_inception, clippy::bool_assert_comparison, unused_imports)]
 mod tests;
 
 use config::Config;
+use domain::SyncTrigger;
 use handlers::AppState;
 use sqlx::postgres::PgPoolOptions;
 use std::time::Duration;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
@@ -28,6 +36,16 @@ async fn main() -> anyhow::Result<()> {
     // Загрузить конфиг
     let config = Config::from_env();
     info!("Config loaded");
+    for (key, url) in [
+        ("NASA_API_URL", &config.nasa_api_url),
+        ("WHERE_ISS_URL", &config.where_iss_url),
+        ("TLE_URL", &config.tle_url),
+        ("JWST_HOST", &config.jwst_api_url),
+    ] {
+        if let Err(e) = validation::validate_url(url) {
+            warn!("{}: {}", key, e);
+        }
+    }
 
     // Инициализировать пул БД с оптимизированными параметрами
     let pool = PgPoolOptions::new()
@@ -55,14 +73,17 @@ async fn main() -> anyhow::Result<()> {
 
     // ============ Фоновые задачи ============
 
-    // ISS фоновый сбор
+    // ISS фоновый сбор (все отслеживаемые спутники)
     {
         let state = state.clone();
         let interval = config.iss_every_seconds;
+        let norad_ids = config.tracked_norad_ids.clone();
         tokio::spawn(async move {
             loop {
-                if let Err(e) = state.iss_service.fetch_and_save().await {
-                    error!("ISS fetch error: {}", e);
+                for &norad_id in &norad_ids {
+                    if let Err(e) = state.ingest_position(norad_id).await {
+                        error!("Satellite {} fetch error: {}", norad_id, e);
+                    }
                 }
                 tokio::time::sleep(Duration::from_secs(interval)).await;
             }
@@ -75,7 +96,7 @@ async fn main() -> anyhow::Result<()> {
         let interval = config.fetch_every_seconds;
         tokio::spawn(async move {
             loop {
-                if let Err(e) = state.osdr_service.sync().await {
+                if let Err(e) = state.osdr_service.sync(None, SyncTrigger::Scheduled).await {
                     error!("OSDR sync error: {}", e);
                 }
                 tokio::time::sleep(Duration::from_secs(interval)).await;
@@ -167,6 +188,36 @@ async fn main() -> anyhow::Result<()> {
         });
     }
 
+    // TLE фоновое обновление (для локальной SGP4-пропагации)
+    {
+        let state = state.clone();
+        let interval = config.tle_every_seconds;
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = state.space_service.refresh_source("tle").await {
+                    error!("TLE refresh error: {}", e);
+                }
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        });
+    }
+
+    // Retention: прореживание и удаление старых iss_fetch_log / space_cache
+    {
+        let state = state.clone();
+        let interval = config.retention_every_seconds;
+        tokio::spawn(async move {
+            loop {
+                match state.retention_service.try_run(SyncTrigger::Scheduled).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => info!("Retention run already in progress, skipping scheduled run"),
+                    Err(e) => error!("Retention run error: {}", e),
+                }
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        });
+    }
+
     // ============ HTTP Server ============
 
     let app = routes::create_router(state, config.clone());
@@ -209,6 +260,43 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// UPDATE, переносящий числа из payload в колонки (числа могут быть и строками)
+fn iss_backfill_sql() -> String {
+    let num = |key: &str| {
+        format!(
+            "CASE WHEN jsonb_typeof(payload->'{k}') = 'number'
+                    OR (jsonb_typeof(payload->'{k}') = 'string'
+                        AND payload->>'{k}' ~ '^\\s*-?[0-9]+(\\.[0-9]+)?([eE][-+]?[0-9]+)?\\s*$')
+                  THEN trim(payload->>'{k}')::float8 END",
+            k = key
+        )
+    };
+
+    format!(
+        "UPDATE iss_fetch_log SET
+            latitude = {lat},
+            longitude = {lon},
+            altitude = {alt},
+            velocity = {vel},
+            visibility = CASE WHEN jsonb_typeof(payload->'visibility') = 'string' THEN payload->>'visibility' END,
+            footprint = {footprint},
+            solar_lat = {solar_lat},
+            solar_lon = {solar_lon},
+            observed_at = to_timestamp({ts})
+         WHERE latitude IS NULL
+           AND {lat} BETWEEN -90 AND 90
+           AND {lon} BETWEEN -180 AND 180",
+        lat = num("latitude"),
+        lon = num("longitude"),
+        alt = num("altitude"),
+        vel = num("velocity"),
+        footprint = num("footprint"),
+        solar_lat = num("solar_lat"),
+        solar_lon = num("solar_lon"),
+        ts = num("timestamp"),
+    )
+}
+
 /// Инициализация БД
 async fn init_db(pool: &sqlx::PgPool) -> anyhow::Result<()> {
     // ISS Fetch Log
@@ -223,6 +311,102 @@ async fn init_db(pool: &sqlx::PgPool) -> anyhow::Result<()> {
     .execute(pool)
     .await?;
 
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS ix_iss_fetch_log_fetched_at ON iss_fetch_log(fetched_at)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Старые записи до появления колонки — это МКС
+    sqlx::query(
+        "ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS norad_id INTEGER NOT NULL DEFAULT 25544",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS ix_iss_fetch_log_norad_fetched_at ON iss_fetch_log(norad_id, fetched_at)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Типизированная телеметрия, разобранная при сохранении. Колонки и backfill старых
+    // записей — в одной транзакции: backfill идёт только при появлении колонок, а не на каждом старте
+    let mut tx = pool.begin().await?;
+    let typed_columns_exist = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (
+            SELECT 1 FROM information_schema.columns
+            WHERE table_schema = current_schema() AND table_name = 'iss_fetch_log' AND column_name = 'latitude'
+        )",
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "ALTER TABLE iss_fetch_log
+            ADD COLUMN IF NOT EXISTS latitude DOUBLE PRECISION,
+            ADD COLUMN IF NOT EXISTS longitude DOUBLE PRECISION,
+            ADD COLUMN IF NOT EXISTS altitude DOUBLE PRECISION,
+            ADD COLUMN IF NOT EXISTS velocity DOUBLE PRECISION,
+            ADD COLUMN IF NOT EXISTS visibility TEXT,
+            ADD COLUMN IF NOT EXISTS footprint DOUBLE PRECISION,
+            ADD COLUMN IF NOT EXISTS solar_lat DOUBLE PRECISION,
+            ADD COLUMN IF NOT EXISTS solar_lon DOUBLE PRECISION,
+            ADD COLUMN IF NOT EXISTS observed_at TIMESTAMPTZ",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Backfill старых записей из payload; битые строки остаются с NULL и не попадают в выборки
+    if !typed_columns_exist {
+        let backfilled = sqlx::query(&iss_backfill_sql()).execute(&mut *tx).await?.rows_affected();
+        info!("Backfilled typed telemetry for {} iss_fetch_log rows", backfilled);
+    }
+    tx.commit().await?;
+
+    // Геозоны и события входа/выхода
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iss_geofences(
+            id BIGSERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            norad_id INTEGER NOT NULL DEFAULT 25544,
+            shape JSONB NOT NULL,
+            enabled BOOLEAN NOT NULL DEFAULT TRUE,
+            inside BOOLEAN NOT NULL DEFAULT FALSE,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS iss_geofence_events(
+            id BIGSERIAL PRIMARY KEY,
+            geofence_id BIGINT NOT NULL REFERENCES iss_geofences(id) ON DELETE CASCADE,
+            norad_id INTEGER NOT NULL,
+            kind TEXT NOT NULL CHECK (kind IN ('enter', 'exit')),
+            occurred_at TIMESTAMPTZ NOT NULL,
+            fetch_id BIGINT,
+            latitude DOUBLE PRECISION NOT NULL,
+            longitude DOUBLE PRECISION NOT NULL
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS ix_iss_geofence_events_occurred_at ON iss_geofence_events(occurred_at DESC)",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS ix_iss_geofence_events_geofence ON iss_geofence_events(geofence_id, occurred_at DESC)",
+    )
+    .execute(pool)
+    .await?;
+
     // OSDR Items
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS osdr_items(
@@ -244,6 +428,189 @@ async fn init_db(pool: &sqlx::PgPool) -> anyhow::Result<()> {
     .execute(pool)
     .await?;
 
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS ix_osdr_status ON osdr_items(status)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Индексы фасетов /osdr/facets: GIN по полям raw и выражение года релиза
+    for (name, facet) in [
+        ("ix_osdr_raw_organism", domain::OsdrFacet::Organism),
+        ("ix_osdr_raw_project_type", domain::OsdrFacet::ProjectType),
+        ("ix_osdr_raw_assay_technology", domain::OsdrFacet::AssayTechnology),
+    ] {
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {} ON osdr_items USING GIN ({})",
+            name,
+            repo::osdr_facet_expr(facet)
+        ))
+        .execute(pool)
+        .await?;
+    }
+
+    sqlx::query(&format!(
+        "CREATE INDEX IF NOT EXISTS ix_osdr_release_year ON osdr_items ({})",
+        repo::osdr_facet_expr(domain::OsdrFacet::ReleaseYear)
+    ))
+    .execute(pool)
+    .await?;
+
+    // Версии OSDR: хэш содержимого текущей записи и ревизии с diff от предыдущей
+    sqlx::query("ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS content_hash TEXT")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(&format!(
+        "UPDATE osdr_items SET content_hash = {} WHERE content_hash IS NULL",
+        repo::content_hash_sql("raw")
+    ))
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS osdr_item_versions(
+            id BIGSERIAL PRIMARY KEY,
+            item_id BIGINT NOT NULL REFERENCES osdr_items(id) ON DELETE CASCADE,
+            dataset_id TEXT,
+            version INT NOT NULL,
+            content_hash TEXT NOT NULL,
+            title TEXT,
+            status TEXT,
+            updated_at TIMESTAMPTZ,
+            raw JSONB NOT NULL,
+            diff JSONB,
+            recorded_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            UNIQUE (item_id, version)
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    // Существующие записи становятся первой ревизией
+    sqlx::query(
+        "INSERT INTO osdr_item_versions (item_id, dataset_id, version, content_hash, title, status, updated_at, raw, recorded_at)
+         SELECT i.id, i.dataset_id, 1, i.content_hash, i.title, i.status, i.updated_at, i.raw, i.inserted_at
+         FROM osdr_items i
+         WHERE NOT EXISTS (SELECT 1 FROM osdr_item_versions v WHERE v.item_id = i.id)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Tombstone OSDR: когда запись последний раз встречалась в выдаче и когда пропала из неё
+    sqlx::query("ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMPTZ")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS withdrawn_at TIMESTAMPTZ")
+        .execute(pool)
+        .await?;
+
+    // Записи без dataset_id сводятся по хэшу содержимого: убираем накопленные копии
+    sqlx::query(
+        "DELETE FROM osdr_items a
+         USING osdr_items b
+         WHERE a.dataset_id IS NULL AND b.dataset_id IS NULL
+           AND a.content_hash = b.content_hash
+           AND a.id > b.id",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS ux_osdr_keyless_content ON osdr_items(content_hash) WHERE dataset_id IS NULL",
+    )
+    .execute(pool)
+    .await?;
+
+    // Журнал прогонов синхронизации
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS sync_runs(
+            id BIGSERIAL PRIMARY KEY,
+            source TEXT NOT NULL,
+            trigger TEXT NOT NULL,
+            mode TEXT NOT NULL,
+            status TEXT NOT NULL,
+            started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            finished_at TIMESTAMPTZ,
+            resumed_from BIGINT NOT NULL DEFAULT 0,
+            pages INT NOT NULL DEFAULT 0,
+            fetched BIGINT NOT NULL DEFAULT 0,
+            inserted BIGINT NOT NULL DEFAULT 0,
+            changed BIGINT NOT NULL DEFAULT 0,
+            unchanged BIGINT NOT NULL DEFAULT 0,
+            failed BIGINT NOT NULL DEFAULT 0,
+            total_hits BIGINT,
+            stopped_early BOOLEAN NOT NULL DEFAULT false,
+            completed BOOLEAN NOT NULL DEFAULT false,
+            upstream_ms_total BIGINT NOT NULL DEFAULT 0,
+            upstream_ms_max BIGINT NOT NULL DEFAULT 0,
+            error_samples JSONB NOT NULL DEFAULT '[]'::jsonb,
+            error TEXT
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("ALTER TABLE sync_runs ADD COLUMN IF NOT EXISTS withdrawn BIGINT NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("ALTER TABLE sync_runs ADD COLUMN IF NOT EXISTS duplicates BIGINT NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS ix_sync_runs_source ON sync_runs(source, id DESC)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Прогоны, оборванные перезапуском сервиса
+    sqlx::query(
+        "UPDATE sync_runs SET status = 'interrupted', finished_at = now() WHERE status = 'running'",
+    )
+    .execute(pool)
+    .await?;
+
+    // Журнал прогонов retention (iss_fetch_log, space_cache)
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS retention_runs(
+            id BIGSERIAL PRIMARY KEY,
+            trigger TEXT NOT NULL,
+            status TEXT NOT NULL,
+            started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            finished_at TIMESTAMPTZ,
+            compacted BIGINT NOT NULL DEFAULT 0,
+            dropped BIGINT NOT NULL DEFAULT 0,
+            scopes JSONB NOT NULL DEFAULT '[]'::jsonb,
+            error TEXT
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "UPDATE retention_runs SET status = 'interrupted', finished_at = now() WHERE status = 'running'",
+    )
+    .execute(pool)
+    .await?;
+
+    // Чекпоинт постраничной синхронизации OSDR
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS osdr_sync_checkpoint(
+            name TEXT PRIMARY KEY,
+            mode TEXT NOT NULL,
+            next_from BIGINT NOT NULL DEFAULT 0,
+            total_hits BIGINT,
+            started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            completed_at TIMESTAMPTZ
+        )",
+    )
+    .execute(pool)
+    .await?;
+
     // Space Cache
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS space_cache(
@@ -262,5 +629,33 @@ async fn init_db(pool: &sqlx::PgPool) -> anyhow::Result<()> {
     .execute(pool)
     .await?;
 
+    // Дедупликация снимков: хэш содержимого и время последнего совпавшего запроса
+    sqlx::query("ALTER TABLE space_cache ADD COLUMN IF NOT EXISTS content_hash TEXT")
+        .execute(pool)
+        .await?;
+
+    sqlx::query("ALTER TABLE space_cache ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMPTZ")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(&format!(
+        "UPDATE space_cache
+         SET content_hash = COALESCE(content_hash, {}),
+             last_seen_at = COALESCE(last_seen_at, fetched_at)
+         WHERE content_hash IS NULL OR last_seen_at IS NULL",
+        repo::content_hash_sql("payload")
+    ))
+    .execute(pool)
+    .await?;
+
+    sqlx::query("ALTER TABLE space_cache ALTER COLUMN last_seen_at SET NOT NULL")
+        .execute(pool)
+        .await?;
+
+    // Расхождение payload'а с типизированной моделью источника
+    sqlx::query("ALTER TABLE space_cache ADD COLUMN IF NOT EXISTS schema_warning TEXT")
+        .execute(pool)
+        .await?;
+
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,341 @@
+use crate::domain::{IssPass, OrbitPosition, PassPoint};
+use crate::error::ApiError;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+
+/// Экваториальный радиус Земли (WGS84), км
+pub const EARTH_RADIUS_KM: f64 = 6378.137;
+/// Сжатие эллипсоида WGS84
+const WGS84_F: f64 = 1.0 / 298.257223563;
+
+/// Астрономическая единица, км
+pub const AU_KM: f64 = 149_597_870.7;
+
+/// Насколько далеко от эпохи TLE доверяем SGP4 (точность падает на км/сутки)
+pub const PROPAGATION_MAX_AGE_DAYS: i64 = 14;
+
+/// SGP4-пропагатор по одному набору элементов TLE
+pub struct OrbitPropagator {
+    elements: sgp4::Elements,
+    constants: sgp4::Constants,
+}
+
+impl OrbitPropagator {
+    /// Создать пропагатор из двух строк TLE
+    pub fn from_tle(name: Option<&str>, line1: &str, line2: &str) -> Result<Self, ApiError> {
+        let elements = sgp4::Elements::from_tle(
+            name.map(str::to_string),
+            line1.trim().as_bytes(),
+            line2.trim().as_bytes(),
+        )
+        .map_err(|e| ApiError::upstream_error(format!("Invalid TLE: {}", e)))?;
+        let constants = sgp4::Constants::from_elements(&elements)
+            .map_err(|e| ApiError::upstream_error(format!("Invalid TLE elements: {}", e)))?;
+
+        Ok(Self { elements, constants })
+    }
+
+    /// Создать пропагатор из payload'а источника `tle` (поля line1/line2/header)
+    pub fn from_payload(payload: &Value) -> Result<Self, ApiError> {
+        let line = |key: &str| {
+            payload
+                .get(key)
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| ApiError::upstream_error(format!("TLE payload has no `{}`", key)))
+        };
+        let name = payload
+            .get("header")
+            .or_else(|| payload.get("name"))
+            .and_then(|v| v.as_str());
+
+        Self::from_tle(name, line("line1")?, line("line2")?)
+    }
+
+    /// NORAD ID спутника из TLE
+    pub fn norad_id(&self) -> u64 {
+        self.elements.norad_id
+    }
+
+    /// Эпоха элементов TLE
+    pub fn epoch(&self) -> DateTime<Utc> {
+        self.elements.datetime.and_utc()
+    }
+
+    /// Попадает ли момент в окно, где пропагации можно доверять
+    pub fn covers(&self, at: DateTime<Utc>) -> bool {
+        (at - self.epoch()).num_seconds().abs() <= Duration::days(PROPAGATION_MAX_AGE_DAYS).num_seconds()
+    }
+
+    /// Положение и скорость в инерциальной системе TEME (км, км/с)
+    pub fn propagate_teme(&self, at: DateTime<Utc>) -> Result<([f64; 3], [f64; 3]), ApiError> {
+        let minutes = self
+            .elements
+            .datetime_to_minutes_since_epoch(&at.naive_utc())
+            .map_err(|e| ApiError::bad_request("VALIDATION_ERROR", format!("Time out of range: {}", e)))?;
+        let prediction = self
+            .constants
+            .propagate(minutes)
+            .map_err(|e| ApiError::internal_error(format!("SGP4 propagation failed: {}", e)))?;
+
+        Ok((prediction.position, prediction.velocity))
+    }
+
+    /// Геодезическое положение спутника в момент `at`
+    pub fn position_at(&self, at: DateTime<Utc>) -> Result<OrbitPosition, ApiError> {
+        if !self.covers(at) {
+            return Err(ApiError::bad_request(
+                "VALIDATION_ERROR",
+                format!(
+                    "`at` must be within {} days of the TLE epoch {}",
+                    PROPAGATION_MAX_AGE_DAYS,
+                    self.epoch().to_rfc3339()
+                ),
+            ));
+        }
+
+        let (position, velocity) = self.propagate_teme(at)?;
+        let ecef = teme_to_ecef(position, gmst_rad(at));
+        let (latitude, longitude, altitude) = ecef_to_geodetic(ecef);
+        let speed_kms = (velocity[0].powi(2) + velocity[1].powi(2) + velocity[2].powi(2)).sqrt();
+
+        Ok(OrbitPosition {
+            at,
+            latitude,
+            longitude,
+            altitude,
+            velocity: speed_kms * 3600.0,
+            tle_epoch: self.epoch(),
+        })
+    }
+
+    /// Положение спутника в ECEF (км) для момента `at`
+    fn ecef_at(&self, at: DateTime<Utc>) -> Result<([f64; 3], [f64; 3]), ApiError> {
+        let (teme, _) = self.propagate_teme(at)?;
+        Ok((teme_to_ecef(teme, gmst_rad(at)), teme))
+    }
+
+    /// Найти пролёты над наблюдателем в [from, to).
+    /// У пролёта, уже идущего в момент `from`, восходом считается сам `from`.
+    pub fn find_passes(
+        &self,
+        observer: &Observer,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<IssPass>, ApiError> {
+        let step = Duration::seconds(PASS_SCAN_STEP_SEC);
+        let elevation = |at: DateTime<Utc>| -> Result<f64, ApiError> {
+            let (ecef, _) = self.ecef_at(at)?;
+            Ok(observer.look_angles(ecef).1)
+        };
+
+        let mut passes = Vec::new();
+        let mut prev_t = from;
+        let mut prev_el = elevation(from)?;
+        let mut rise = (prev_el >= 0.0).then_some(from);
+
+        let mut t = from + step;
+        while t <= to {
+            let el = elevation(t)?;
+            if prev_el < 0.0 && el >= 0.0 {
+                rise = Some(Self::refine_crossing(&elevation, prev_t, t)?);
+            } else if prev_el >= 0.0 && el < 0.0 {
+                if let Some(rise_at) = rise.take() {
+                    let set_at = Self::refine_crossing(&elevation, prev_t, t)?;
+                    passes.push(self.describe_pass(observer, rise_at, set_at)?);
+                }
+            }
+            prev_t = t;
+            prev_el = el;
+            t += step;
+        }
+
+        Ok(passes)
+    }
+
+    /// Уточнить момент пересечения горизонта бисекцией до 1 секунды
+    fn refine_crossing<F>(elevation: &F, mut lo: DateTime<Utc>, mut hi: DateTime<Utc>) -> Result<DateTime<Utc>, ApiError>
+    where
+        F: Fn(DateTime<Utc>) -> Result<f64, ApiError>,
+    {
+        let rising = elevation(lo)? < 0.0;
+        while (hi - lo).num_milliseconds() > 1000 {
+            let mid = lo + (hi - lo) / 2;
+            let above = elevation(mid)? >= 0.0;
+            if above == rising {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        Ok(hi)
+    }
+
+    /// Собрать описание пролёта: кульминация, азимуты, освещённость
+    fn describe_pass(&self, observer: &Observer, rise: DateTime<Utc>, set: DateTime<Utc>) -> Result<IssPass, ApiError> {
+        let point = |at: DateTime<Utc>| -> Result<(PassPoint, bool, bool), ApiError> {
+            let (ecef, teme) = self.ecef_at(at)?;
+            let (azimuth_deg, elevation_deg) = observer.look_angles(ecef);
+            let sun = sun_position_eci(at);
+            let sun_ecef = teme_to_ecef(sun, gmst_rad(at));
+            let sunlit = is_sunlit(teme, sun);
+            let observer_dark = observer.look_angles(sun_ecef).1 < OBSERVER_DARK_SUN_ELEVATION_DEG;
+            Ok((PassPoint { time: at, azimuth_deg, elevation_deg }, sunlit, observer_dark))
+        };
+
+        let mut culmination = point(rise)?.0;
+        let mut sunlit = false;
+        let mut observer_dark = false;
+        let mut visible = false;
+
+        let mut t = rise;
+        while t <= set {
+            let (p, lit, dark) = point(t)?;
+            if p.elevation_deg > culmination.elevation_deg {
+                culmination = p;
+            }
+            sunlit |= lit;
+            observer_dark |= dark;
+            visible |= lit && dark;
+            t += Duration::seconds(PASS_DETAIL_STEP_SEC);
+        }
+
+        let max_elevation_deg = culmination.elevation_deg;
+        Ok(IssPass {
+            rise: point(rise)?.0,
+            culmination,
+            set: point(set)?.0,
+            duration_sec: (set - rise).num_seconds(),
+            max_elevation_deg,
+            sunlit,
+            observer_dark,
+            visible,
+        })
+    }
+}
+
+/// Шаг грубого поиска восходов/заходов (пролёт МКС длится от ~2 минут)
+const PASS_SCAN_STEP_SEC: i64 = 30;
+/// Шаг детализации внутри пролёта
+const PASS_DETAIL_STEP_SEC: i64 = 5;
+/// Солнце ниже −6° — гражданские сумерки закончились, спутник можно увидеть
+const OBSERVER_DARK_SUN_ELEVATION_DEG: f64 = -6.0;
+
+/// Наблюдатель на поверхности Земли
+#[derive(Debug, Clone)]
+pub struct Observer {
+    pub lat_deg: f64,
+    pub lon_deg: f64,
+    ecef: [f64; 3],
+}
+
+impl Observer {
+    pub fn new(lat_deg: f64, lon_deg: f64, alt_km: f64) -> Self {
+        Self { lat_deg, lon_deg, ecef: geodetic_to_ecef(lat_deg, lon_deg, alt_km) }
+    }
+
+    /// Азимут (от севера по часовой) и угол места цели, заданной в ECEF, градусы
+    pub fn look_angles(&self, target_ecef: [f64; 3]) -> (f64, f64) {
+        let rho = [
+            target_ecef[0] - self.ecef[0],
+            target_ecef[1] - self.ecef[1],
+            target_ecef[2] - self.ecef[2],
+        ];
+        let (sin_lat, cos_lat) = self.lat_deg.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.lon_deg.to_radians().sin_cos();
+
+        let east = -sin_lon * rho[0] + cos_lon * rho[1];
+        let north = -sin_lat * cos_lon * rho[0] - sin_lat * sin_lon * rho[1] + cos_lat * rho[2];
+        let up = cos_lat * cos_lon * rho[0] + cos_lat * sin_lon * rho[1] + sin_lat * rho[2];
+
+        let azimuth = east.atan2(north).to_degrees().rem_euclid(360.0);
+        let elevation = up.atan2((east * east + north * north).sqrt()).to_degrees();
+        (azimuth, elevation)
+    }
+}
+
+/// Положение Солнца в экваториальной инерциальной системе, км
+/// (упрощённая модель Astronomical Almanac, точность ~0.01°)
+pub fn sun_position_eci(at: DateTime<Utc>) -> [f64; 3] {
+    let n = julian_date(at) - 2_451_545.0;
+    let mean_lon = (280.460 + 0.985_647_4 * n).rem_euclid(360.0);
+    let g = (357.528 + 0.985_600_3 * n).rem_euclid(360.0).to_radians();
+    let ecliptic_lon = (mean_lon + 1.915 * g.sin() + 0.020 * (2.0 * g).sin()).to_radians();
+    let obliquity = (23.439 - 0.000_000_4 * n).to_radians();
+    let distance = (1.000_14 - 0.016_71 * g.cos() - 0.000_14 * (2.0 * g).cos()) * AU_KM;
+
+    [
+        distance * ecliptic_lon.cos(),
+        distance * obliquity.cos() * ecliptic_lon.sin(),
+        distance * obliquity.sin() * ecliptic_lon.sin(),
+    ]
+}
+
+/// Освещён ли спутник Солнцем (цилиндрическая модель тени Земли)
+pub fn is_sunlit(sat_eci: [f64; 3], sun_eci: [f64; 3]) -> bool {
+    let sun_norm = (sun_eci[0].powi(2) + sun_eci[1].powi(2) + sun_eci[2].powi(2)).sqrt();
+    let s = [sun_eci[0] / sun_norm, sun_eci[1] / sun_norm, sun_eci[2] / sun_norm];
+    let along = sat_eci[0] * s[0] + sat_eci[1] * s[1] + sat_eci[2] * s[2];
+    if along >= 0.0 {
+        return true;
+    }
+    let perp = [sat_eci[0] - along * s[0], sat_eci[1] - along * s[1], sat_eci[2] - along * s[2]];
+    (perp[0].powi(2) + perp[1].powi(2) + perp[2].powi(2)).sqrt() > EARTH_RADIUS_KM
+}
+
+/// Гринвичское среднее звёздное время (IAU-82), радианы
+pub fn gmst_rad(at: DateTime<Utc>) -> f64 {
+    let jd = julian_date(at);
+    let t = (jd - 2_451_545.0) / 36_525.0;
+    let gmst_sec = 67_310.548_41
+        + (876_600.0 * 3600.0 + 8_640_184.812_866) * t
+        + 0.093_104 * t * t
+        - 6.2e-6 * t * t * t;
+    (gmst_sec.rem_euclid(86_400.0) / 240.0).to_radians()
+}
+
+/// Юлианская дата (UTC ≈ UT1)
+pub fn julian_date(at: DateTime<Utc>) -> f64 {
+    at.timestamp_millis() as f64 / 86_400_000.0 + 2_440_587.5
+}
+
+/// Поворот TEME → ECEF на угол звёздного времени (без учёта движения полюса)
+pub fn teme_to_ecef(r: [f64; 3], gmst: f64) -> [f64; 3] {
+    let (s, c) = gmst.sin_cos();
+    [c * r[0] + s * r[1], -s * r[0] + c * r[1], r[2]]
+}
+
+/// ECEF (км) → широта, долгота (градусы, [-180, 180)) и высота над эллипсоидом (км)
+pub fn ecef_to_geodetic(r: [f64; 3]) -> (f64, f64, f64) {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let p = (r[0] * r[0] + r[1] * r[1]).sqrt();
+    let lon = r[1].atan2(r[0]);
+
+    let mut lat = r[2].atan2(p * (1.0 - e2));
+    for _ in 0..5 {
+        let n = EARTH_RADIUS_KM / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        lat = (r[2] + e2 * n * lat.sin()).atan2(p);
+    }
+
+    // Формула высоты без деления на cos(lat) — устойчива над полюсами
+    let n = EARTH_RADIUS_KM / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    let height = p * lat.cos() + (r[2] + e2 * n * lat.sin()) * lat.sin() - n;
+
+    (lat.to_degrees(), normalize_lon(lon.to_degrees()), height)
+}
+
+/// Геодезические координаты (градусы, км) → ECEF (км)
+pub fn geodetic_to_ecef(lat_deg: f64, lon_deg: f64, alt_km: f64) -> [f64; 3] {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let (lat, lon) = (lat_deg.to_radians(), lon_deg.to_radians());
+    let n = EARTH_RADIUS_KM / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    [
+        (n + alt_km) * lat.cos() * lon.cos(),
+        (n + alt_km) * lat.cos() * lon.sin(),
+        (n * (1.0 - e2) + alt_km) * lat.sin(),
+    ]
+}
+
+/// Привести долготу к диапазону [-180, 180)
+pub fn normalize_lon(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
@@ -2,24 +2,32 @@ use crate::domain::*;
 use crate::error::ApiError;
 use chrono::{DateTime, Utc};
 use serde_json::Value;
-use sqlx::{PgPool, Row};
+use sqlx::pool::PoolConnection;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use futures::TryStreamExt;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
 
 // Advisory lock IDs для разных таблиц
 const ISS_LOCK_ID: i64 = 1001;
 const OSDR_LOCK_ID: i64 = 1002;
 const SPACE_CACHE_LOCK_ID: i64 = 1003;
+const RETENTION_LOCK_ID: i64 = 1004;
 
 /// ISS Repository - работа с данными МКС
 pub struct IssRepository;
 
 impl IssRepository {
-    /// Получить последний запрос ISS
-    pub async fn get_last(pool: &PgPool) -> Result<Option<IssFetchLog>, ApiError> {
+    /// Получить последний запрос по спутнику
+    pub async fn get_last(pool: &PgPool, norad_id: i32) -> Result<Option<IssFetchLog>, ApiError> {
         let row = sqlx::query_as::<_, IssFetchLog>(
-            "SELECT id, fetched_at, source_url, payload
+            "SELECT id, norad_id, fetched_at, source_url, payload,
+                    latitude, longitude, altitude, velocity, visibility, footprint, solar_lat, solar_lon, observed_at
              FROM iss_fetch_log
-             ORDER BY id DESC LIMIT 1"
+             WHERE norad_id = $1 AND latitude IS NOT NULL
+             ORDER BY fetched_at DESC, id DESC LIMIT 1"
         )
+        .bind(norad_id)
         .fetch_optional(pool)
         .await?;
 
@@ -27,7 +35,13 @@ impl IssRepository {
     }
 
     /// Сохранить новый ISS fetch с защитой от наложения (advisory lock)
-    pub async fn save(pool: &PgPool, source_url: &str, payload: Value) -> Result<IssFetchLog, ApiError> {
+    pub async fn save(
+        pool: &PgPool,
+        norad_id: i32,
+        source_url: &str,
+        payload: Value,
+        position: &IssPosition,
+    ) -> Result<IssFetchLog, ApiError> {
         // Advisory lock для защиты от одновременных записей
         sqlx::query("SELECT pg_advisory_lock($1)")
             .bind(ISS_LOCK_ID)
@@ -35,13 +49,26 @@ impl IssRepository {
             .await?;
 
         let result = sqlx::query_as::<_, IssFetchLog>(
-            "INSERT INTO iss_fetch_log (source_url, payload, fetched_at)
-             VALUES ($1, $2, $3)
-             RETURNING id, fetched_at, source_url, payload"
+            "INSERT INTO iss_fetch_log (norad_id, source_url, payload, fetched_at,
+                                        latitude, longitude, altitude, velocity, visibility,
+                                        footprint, solar_lat, solar_lon, observed_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+             RETURNING id, norad_id, fetched_at, source_url, payload,
+                       latitude, longitude, altitude, velocity, visibility, footprint, solar_lat, solar_lon, observed_at"
         )
+        .bind(norad_id)
         .bind(source_url)
         .bind(&payload)
         .bind(Utc::now())
+        .bind(position.latitude)
+        .bind(position.longitude)
+        .bind(position.altitude)
+        .bind(position.velocity)
+        .bind(&position.visibility)
+        .bind(position.footprint)
+        .bind(position.solar_lat)
+        .bind(position.solar_lon)
+        .bind(position.timestamp)
         .fetch_one(pool)
         .await;
 
@@ -54,13 +81,245 @@ impl IssRepository {
         result.map_err(ApiError::from)
     }
 
-    /// Получить тренд (последние 2 записи)
-    pub async fn get_trend(pool: &PgPool) -> Result<Vec<IssFetchLog>, ApiError> {
+    /// Получить записи с id больше after_id (для дозагрузки пропущенных событий)
+    pub async fn get_after_id(
+        pool: &PgPool,
+        norad_id: i32,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<IssFetchLog>, ApiError> {
+        let rows = sqlx::query_as::<_, IssFetchLog>(
+            "SELECT id, norad_id, fetched_at, source_url, payload,
+                    latitude, longitude, altitude, velocity, visibility, footprint, solar_lat, solar_lon, observed_at
+             FROM iss_fetch_log
+             WHERE norad_id = $1 AND latitude IS NOT NULL AND id > $2
+             ORDER BY id ASC
+             LIMIT $3"
+        )
+        .bind(norad_id)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Получить все записи начиная с момента since (по возрастанию времени)
+    pub async fn get_since(pool: &PgPool, norad_id: i32, since: DateTime<Utc>) -> Result<Vec<IssFetchLog>, ApiError> {
         let rows = sqlx::query_as::<_, IssFetchLog>(
-            "SELECT id, fetched_at, source_url, payload
+            "SELECT id, norad_id, fetched_at, source_url, payload,
+                    latitude, longitude, altitude, velocity, visibility, footprint, solar_lat, solar_lon, observed_at
              FROM iss_fetch_log
-             ORDER BY id DESC LIMIT 2"
+             WHERE norad_id = $1 AND latitude IS NOT NULL AND fetched_at >= $2
+             ORDER BY fetched_at ASC"
         )
+        .bind(norad_id)
+        .bind(since)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Получить трек за интервал [from, to) с даунсэмплингом:
+    /// по одной точке (первой по времени) на каждый бакет длиной step_sec
+    pub async fn get_history(
+        pool: &PgPool,
+        norad_id: i32,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        step_sec: i64,
+    ) -> Result<Vec<IssHistoryPoint>, ApiError> {
+        let rows = sqlx::query_as::<_, IssHistoryPoint>(
+            "SELECT DISTINCT ON (bucket) id, fetched_at, latitude, longitude, altitude, velocity, samples
+             FROM (
+                 SELECT id, fetched_at, latitude, longitude, altitude, velocity,
+                        floor(extract(epoch FROM fetched_at) / $3)::bigint AS bucket,
+                        COUNT(*) OVER (PARTITION BY floor(extract(epoch FROM fetched_at) / $3)) AS samples
+                 FROM iss_fetch_log
+                 WHERE norad_id = $4 AND fetched_at >= $1 AND fetched_at < $2
+                   AND latitude IS NOT NULL AND longitude IS NOT NULL
+             ) b
+             ORDER BY bucket, fetched_at ASC"
+        )
+        .bind(from)
+        .bind(to)
+        .bind(step_sec as f64)
+        .bind(norad_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+}
+
+/// Geofence Repository - геозоны и события входа/выхода
+pub struct GeofenceRepository;
+
+impl GeofenceRepository {
+    /// Все геозоны
+    pub async fn list(pool: &PgPool) -> Result<Vec<Geofence>, ApiError> {
+        let rows = sqlx::query_as::<_, Geofence>(
+            "SELECT id, name, norad_id, shape, enabled, inside, created_at, updated_at
+             FROM iss_geofences
+             ORDER BY id ASC"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Включённые геозоны для спутника
+    pub async fn list_enabled(pool: &PgPool, norad_id: i32) -> Result<Vec<Geofence>, ApiError> {
+        let rows = sqlx::query_as::<_, Geofence>(
+            "SELECT id, name, norad_id, shape, enabled, inside, created_at, updated_at
+             FROM iss_geofences
+             WHERE enabled AND norad_id = $1"
+        )
+        .bind(norad_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn get(pool: &PgPool, id: i64) -> Result<Option<Geofence>, ApiError> {
+        let row = sqlx::query_as::<_, Geofence>(
+            "SELECT id, name, norad_id, shape, enabled, inside, created_at, updated_at
+             FROM iss_geofences
+             WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    pub async fn create(
+        pool: &PgPool,
+        name: &str,
+        norad_id: i32,
+        shape: &GeofenceShape,
+        enabled: bool,
+    ) -> Result<Geofence, ApiError> {
+        let row = sqlx::query_as::<_, Geofence>(
+            "INSERT INTO iss_geofences (name, norad_id, shape, enabled)
+             VALUES ($1, $2, $3, $4)
+             RETURNING id, name, norad_id, shape, enabled, inside, created_at, updated_at"
+        )
+        .bind(name)
+        .bind(norad_id)
+        .bind(sqlx::types::Json(shape))
+        .bind(enabled)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Заменить геозону; состояние inside сохраняется — следующая проверка
+    /// зафиксирует выход, если спутник оказался вне новой формы
+    pub async fn update(
+        pool: &PgPool,
+        id: i64,
+        name: &str,
+        norad_id: i32,
+        shape: &GeofenceShape,
+        enabled: bool,
+    ) -> Result<Option<Geofence>, ApiError> {
+        let row = sqlx::query_as::<_, Geofence>(
+            "UPDATE iss_geofences
+             SET name = $2, norad_id = $3, shape = $4, enabled = $5, updated_at = now()
+             WHERE id = $1
+             RETURNING id, name, norad_id, shape, enabled, inside, created_at, updated_at"
+        )
+        .bind(id)
+        .bind(name)
+        .bind(norad_id)
+        .bind(sqlx::types::Json(shape))
+        .bind(enabled)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Удалить геозону вместе с её событиями
+    pub async fn delete(pool: &PgPool, id: i64) -> Result<bool, ApiError> {
+        let result = sqlx::query("DELETE FROM iss_geofences WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Зафиксировать смену состояния и записать событие.
+    /// Условный UPDATE защищает от двойной записи при параллельных проверках.
+    pub async fn record_transition(
+        pool: &PgPool,
+        geofence_id: i64,
+        inside: bool,
+        log: &IssFetchLog,
+    ) -> Result<bool, ApiError> {
+        let mut tx = pool.begin().await?;
+
+        let changed = sqlx::query(
+            "UPDATE iss_geofences SET inside = $2 WHERE id = $1 AND inside <> $2"
+        )
+        .bind(geofence_id)
+        .bind(inside)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+
+        if changed {
+            sqlx::query(
+                "INSERT INTO iss_geofence_events (geofence_id, norad_id, kind, occurred_at, fetch_id, latitude, longitude)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)"
+            )
+            .bind(geofence_id)
+            .bind(log.norad_id)
+            .bind(if inside { "enter" } else { "exit" })
+            .bind(log.position.timestamp.unwrap_or(log.fetched_at))
+            .bind(log.id)
+            .bind(log.position.latitude)
+            .bind(log.position.longitude)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(changed)
+    }
+
+    /// События геозон, новые первыми
+    pub async fn list_events(
+        pool: &PgPool,
+        geofence_id: Option<i64>,
+        norad_id: Option<i32>,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<GeofenceEvent>, ApiError> {
+        let rows = sqlx::query_as::<_, GeofenceEvent>(
+            "SELECT e.id, e.geofence_id, g.name AS geofence_name, e.norad_id, e.kind,
+                    e.occurred_at, e.fetch_id, e.latitude, e.longitude
+             FROM iss_geofence_events e
+             JOIN iss_geofences g ON g.id = e.geofence_id
+             WHERE ($1::bigint IS NULL OR e.geofence_id = $1)
+               AND ($2::int IS NULL OR e.norad_id = $2)
+               AND ($3::timestamptz IS NULL OR e.occurred_at >= $3)
+             ORDER BY e.occurred_at DESC, e.id DESC
+             LIMIT $4"
+        )
+        .bind(geofence_id)
+        .bind(norad_id)
+        .bind(since)
+        .bind(limit)
         .fetch_all(pool)
         .await?;
 
@@ -72,25 +331,139 @@ impl IssRepository {
 pub struct OsdrRepository;
 
 impl OsdrRepository {
-    /// Получить список OSDR items
-    pub async fn list(pool: &PgPool, limit: i64) -> Result<Vec<OsdrItem>, ApiError> {
-        let rows = sqlx::query_as::<_, OsdrItem>(
-            "SELECT id, dataset_id, title, status, updated_at, inserted_at, raw
+    /// Страница OSDR items по фильтрам: keyset по (ключ сортировки, id) после курсора, до `limit` строк
+    pub async fn list(pool: &PgPool, filter: &OsdrListFilter, limit: i64) -> Result<Vec<OsdrItem>, ApiError> {
+        let mut qb = QueryBuilder::<Postgres>::new(
+            "SELECT id, dataset_id, title, status, updated_at, inserted_at, withdrawn_at, raw
              FROM osdr_items
-             ORDER BY inserted_at DESC
-             LIMIT $1"
+             WHERE TRUE",
+        );
+        push_osdr_filters(&mut qb, filter);
+
+        let sort_expr = osdr_sort_expr(filter.sort_by);
+        if let Some(after) = &filter.after {
+            let (op, sql_type) = (
+                if filter.descending { "<" } else { ">" },
+                match filter.sort_by {
+                    OsdrSortBy::InsertedAt | OsdrSortBy::UpdatedAt => "timestamptz",
+                    OsdrSortBy::Title | OsdrSortBy::DatasetId => "text",
+                },
+            );
+            qb.push(format!(" AND ({}, id) {} (CAST(", sort_expr, op))
+                .push_bind(&after.value)
+                .push(format!(" AS {}), ", sql_type))
+                .push_bind(after.id)
+                .push(")");
+        }
+
+        let direction = if filter.descending { "DESC" } else { "ASC" };
+        qb.push(format!(" ORDER BY {} {dir}, id {dir} LIMIT ", sort_expr, dir = direction))
+            .push_bind(limit);
+
+        let rows = qb.build_query_as::<OsdrItem>().fetch_all(pool).await?;
+        Ok(rows)
+    }
+
+    /// Построчно отдать записи под фильтрами списка в `sink` (курсор sqlx, без буферизации таблицы).
+    /// Лимит и курсор страницы не учитываются; закрытый `sink` (клиент ушёл) останавливает выборку.
+    pub async fn export_rows(
+        pool: &PgPool,
+        filter: &OsdrListFilter,
+        sink: &mpsc::Sender<Result<OsdrItem, String>>,
+    ) -> Result<(), ApiError> {
+        let mut qb = QueryBuilder::<Postgres>::new(
+            "SELECT id, dataset_id, title, status, updated_at, inserted_at, withdrawn_at, raw
+             FROM osdr_items
+             WHERE TRUE",
+        );
+        push_osdr_filters(&mut qb, filter);
+
+        let direction = if filter.descending { "DESC" } else { "ASC" };
+        qb.push(format!(
+            " ORDER BY {} {dir}, id {dir}",
+            osdr_sort_expr(filter.sort_by),
+            dir = direction
+        ));
+
+        let mut rows = qb.build_query_as::<OsdrItem>().fetch(pool);
+        while let Some(item) = rows.try_next().await? {
+            if sink.send(Ok(item)).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Количество записей под фильтрами списка (курсор не учитывается)
+    pub async fn count_matching(pool: &PgPool, filter: &OsdrListFilter) -> Result<i64, ApiError> {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM osdr_items WHERE TRUE");
+        push_osdr_filters(&mut qb, filter);
+
+        let count: i64 = qb.build_query_scalar().fetch_one(pool).await?;
+        Ok(count)
+    }
+
+    /// Счётчики значений фасета под фильтрами списка; года — по убыванию, остальное — по частоте
+    pub async fn facet_counts(
+        pool: &PgPool,
+        facet: OsdrFacet,
+        filter: &OsdrListFilter,
+        limit: i64,
+    ) -> Result<Vec<OsdrFacetCount>, ApiError> {
+        let mut qb = match facet.raw_key() {
+            Some(_) => QueryBuilder::<Postgres>::new(format!(
+                "SELECT v.value AS value, COUNT(DISTINCT id) AS count
+                 FROM osdr_items,
+                      LATERAL jsonb_array_elements_text(
+                          CASE jsonb_typeof({expr})
+                              WHEN 'array' THEN {expr}
+                              WHEN 'string' THEN jsonb_build_array({expr})
+                              ELSE '[]'::jsonb
+                          END
+                      ) AS v(value)
+                 WHERE TRUE",
+                expr = osdr_facet_expr(facet)
+            )),
+            None => QueryBuilder::<Postgres>::new(format!(
+                "SELECT ({expr})::text AS value, COUNT(*) AS count
+                 FROM osdr_items
+                 WHERE {expr} IS NOT NULL",
+                expr = osdr_facet_expr(facet)
+            )),
+        };
+        push_osdr_filters(&mut qb, filter);
+
+        let order = if facet.raw_key().is_some() { "count DESC, value" } else { "value DESC" };
+        qb.push(format!(" GROUP BY 1 ORDER BY {} LIMIT ", order))
+            .push_bind(limit);
+
+        let rows = qb.build_query_as::<OsdrFacetCount>().fetch_all(pool).await?;
+        Ok(rows)
+    }
+
+    /// Сохранённые даты релиза (updated_at) по списку dataset_id
+    pub async fn release_dates(
+        pool: &PgPool,
+        dataset_ids: &[String],
+    ) -> Result<HashMap<String, Option<DateTime<Utc>>>, ApiError> {
+        let rows = sqlx::query(
+            "SELECT dataset_id, updated_at FROM osdr_items WHERE dataset_id = ANY($1)"
         )
-        .bind(limit)
+        .bind(dataset_ids)
         .fetch_all(pool)
         .await?;
 
-        Ok(rows)
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get("dataset_id"), row.get("updated_at")))
+            .collect())
     }
 
     /// Получить по ID датасета
     pub async fn get_by_dataset_id(pool: &PgPool, dataset_id: &str) -> Result<Option<OsdrItem>, ApiError> {
         let row = sqlx::query_as::<_, OsdrItem>(
-            "SELECT id, dataset_id, title, status, updated_at, inserted_at, raw
+            "SELECT id, dataset_id, title, status, updated_at, inserted_at, withdrawn_at, raw
              FROM osdr_items
              WHERE dataset_id = $1"
         )
@@ -101,61 +474,193 @@ impl OsdrRepository {
         Ok(row)
     }
 
-    /// Upsert по dataset_id (вставить или обновить по бизнес-ключу)
-    pub async fn upsert(
-        pool: &PgPool,
-        dataset_id: Option<&str>,
-        title: Option<String>,
-        status: Option<String>,
-        updated_at: Option<DateTime<Utc>>,
-        raw: Value,
-    ) -> Result<OsdrItem, ApiError> {
-        // Advisory lock для защиты от одновременных записей
-        sqlx::query("SELECT pg_advisory_lock($1)")
+    /// Пакетный upsert страницы в одной транзакции (UNNEST, один round trip на запрос).
+    /// Записи с dataset_id сводятся по нему, без dataset_id — по хэшу содержимого.
+    /// Каждая строка отмечается `last_seen_at` (и снимается с tombstone); новая версия
+    /// в `osdr_item_versions` пишется только для новых и изменившихся записей.
+    /// Ключи в пакете должны быть уникальны; по одному результату на строку, порядок не сохраняется.
+    pub async fn upsert_batch(pool: &PgPool, rows: &[OsdrUpsertRow]) -> Result<Vec<OsdrUpsertOutcome>, ApiError> {
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut tx = pool.begin().await?;
+
+        // Блокировка до конца транзакции — параллельные sync не пересчитают версии одновременно
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
             .bind(OSDR_LOCK_ID)
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
 
-        let result = if let Some(ds_id) = dataset_id {
-            sqlx::query_as::<_, OsdrItem>(
-                "INSERT INTO osdr_items (dataset_id, title, status, updated_at, raw)
-                 VALUES ($1, $2, $3, $4, $5)
-                 ON CONFLICT (dataset_id) DO UPDATE
-                 SET title = EXCLUDED.title,
-                     status = EXCLUDED.status,
-                     updated_at = EXCLUDED.updated_at,
-                     raw = EXCLUDED.raw
-                 RETURNING id, dataset_id, title, status, updated_at, inserted_at, raw"
-            )
-            .bind(ds_id)
-            .bind(&title)
-            .bind(&status)
-            .bind(updated_at)
-            .bind(&raw)
-            .fetch_one(pool)
-            .await
-        } else {
-            sqlx::query_as::<_, OsdrItem>(
-                "INSERT INTO osdr_items (dataset_id, title, status, updated_at, raw)
-                 VALUES ($1, $2, $3, $4, $5)
-                 RETURNING id, dataset_id, title, status, updated_at, inserted_at, raw"
+        let (keyed, keyless): (Vec<&OsdrUpsertRow>, Vec<&OsdrUpsertRow>) =
+            rows.iter().partition(|r| r.dataset_id.is_some());
+
+        let keys: Vec<String> = keyed.iter().filter_map(|r| r.dataset_id.clone()).collect();
+        let previous: HashMap<String, (Value, Option<String>)> = sqlx::query(
+            "SELECT dataset_id, raw, content_hash FROM osdr_items WHERE dataset_id = ANY($1) FOR UPDATE"
+        )
+        .bind(&keys)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.get("dataset_id"), (row.get("raw"), row.get("content_hash"))))
+        .collect();
+
+        // xmax = 0 только у только что вставленной строки
+        let mut written = Vec::with_capacity(rows.len());
+        if !keyed.is_empty() {
+            written.extend(
+                Self::bind_rows(
+                    sqlx::query(&format!(
+                        "INSERT INTO osdr_items (dataset_id, title, status, updated_at, raw, content_hash, last_seen_at)
+                         SELECT dataset_id, title, status, updated_at, raw, {}, now()
+                         FROM UNNEST($1::text[], $2::text[], $3::text[], $4::timestamptz[], $5::jsonb[])
+                              AS t(dataset_id, title, status, updated_at, raw)
+                         ON CONFLICT (dataset_id) DO UPDATE
+                         SET title = EXCLUDED.title,
+                             status = EXCLUDED.status,
+                             updated_at = EXCLUDED.updated_at,
+                             raw = EXCLUDED.raw,
+                             content_hash = EXCLUDED.content_hash,
+                             last_seen_at = now(),
+                             withdrawn_at = NULL
+                         RETURNING id, dataset_id, raw, content_hash, (xmax = 0) AS inserted",
+                        content_hash_sql("raw")
+                    )),
+                    &keyed,
+                )
+                .fetch_all(&mut *tx)
+                .await?,
+            );
+        }
+        if !keyless.is_empty() {
+            written.extend(
+                Self::bind_rows(
+                    sqlx::query(&format!(
+                        "INSERT INTO osdr_items (dataset_id, title, status, updated_at, raw, content_hash, last_seen_at)
+                         SELECT dataset_id, title, status, updated_at, raw, {}, now()
+                         FROM UNNEST($1::text[], $2::text[], $3::text[], $4::timestamptz[], $5::jsonb[])
+                              AS t(dataset_id, title, status, updated_at, raw)
+                         ON CONFLICT (content_hash) WHERE dataset_id IS NULL DO UPDATE
+                         SET last_seen_at = now(),
+                             withdrawn_at = NULL
+                         RETURNING id, dataset_id, raw, content_hash, (xmax = 0) AS inserted",
+                        content_hash_sql("raw")
+                    )),
+                    &keyless,
+                )
+                .fetch_all(&mut *tx)
+                .await?,
+            );
+        }
+
+        let mut outcomes = Vec::with_capacity(written.len());
+        let mut version_items = Vec::new();
+        let mut version_diffs = Vec::new();
+        for row in &written {
+            let previous = row
+                .get::<Option<String>, _>("dataset_id")
+                .and_then(|key| previous.get(&key));
+            let (outcome, diff) = match (row.get::<bool, _>("inserted"), previous) {
+                (true, _) => (OsdrUpsertOutcome::Inserted, None),
+                // Запись без dataset_id найдена по хэшу — содержимое то же
+                (false, None) => (OsdrUpsertOutcome::Unchanged, None),
+                (false, Some((_, old_hash))) if *old_hash == row.get::<Option<String>, _>("content_hash") => {
+                    (OsdrUpsertOutcome::Unchanged, None)
+                }
+                (false, Some((old_raw, _))) => {
+                    let patch = crate::diff::json_patch(old_raw, &row.get::<Value, _>("raw"));
+                    (OsdrUpsertOutcome::Changed, Some(Value::Array(patch)))
+                }
+            };
+            if outcome != OsdrUpsertOutcome::Unchanged {
+                version_items.push(row.get::<i64, _>("id"));
+                version_diffs.push(diff);
+            }
+            outcomes.push(outcome);
+        }
+
+        if !version_items.is_empty() {
+            sqlx::query(
+                "INSERT INTO osdr_item_versions (item_id, dataset_id, version, content_hash, title, status, updated_at, raw, diff)
+                 SELECT i.id, i.dataset_id,
+                        COALESCE((SELECT MAX(v.version) FROM osdr_item_versions v WHERE v.item_id = i.id), 0) + 1,
+                        i.content_hash, i.title, i.status, i.updated_at, i.raw, d.diff
+                 FROM UNNEST($1::bigint[], $2::jsonb[]) AS d(item_id, diff)
+                 JOIN osdr_items i ON i.id = d.item_id"
             )
-            .bind::<Option<String>>(None)
-            .bind(&title)
-            .bind(&status)
-            .bind(updated_at)
-            .bind(&raw)
-            .fetch_one(pool)
-            .await
-        };
+            .bind(&version_items)
+            .bind(&version_diffs)
+            .execute(&mut *tx)
+            .await?;
+        }
 
-        // Освободить lock
-        let _ = sqlx::query("SELECT pg_advisory_unlock($1)")
-            .bind(OSDR_LOCK_ID)
-            .execute(pool)
-            .await;
+        tx.commit().await?;
+        Ok(outcomes)
+    }
 
-        result.map_err(ApiError::from)
+    /// Колонки строк пакета как массивы для UNNEST ($1..$5)
+    fn bind_rows<'q>(
+        query: sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments>,
+        rows: &[&OsdrUpsertRow],
+    ) -> sqlx::query::Query<'q, Postgres, sqlx::postgres::PgArguments> {
+        query
+            .bind(rows.iter().map(|r| r.dataset_id.clone()).collect::<Vec<_>>())
+            .bind(rows.iter().map(|r| r.title.clone()).collect::<Vec<_>>())
+            .bind(rows.iter().map(|r| r.status.clone()).collect::<Vec<_>>())
+            .bind(rows.iter().map(|r| r.updated_at).collect::<Vec<_>>())
+            .bind(rows.iter().map(|r| r.raw.clone()).collect::<Vec<_>>())
+    }
+
+    /// Активные записи и сколько из них не встречалось с начала обхода (кандидаты в withdrawn)
+    pub async fn count_unseen(pool: &PgPool, traversal_started_at: DateTime<Utc>) -> Result<(i64, i64), ApiError> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) AS active,
+                    COUNT(*) FILTER (WHERE last_seen_at IS NULL OR last_seen_at < $1) AS unseen
+             FROM osdr_items
+             WHERE withdrawn_at IS NULL"
+        )
+        .bind(traversal_started_at)
+        .fetch_one(pool)
+        .await?;
+
+        Ok((row.get("active"), row.get("unseen")))
+    }
+
+    /// Tombstone: записи, не встреченные с начала полного обхода, помечаются withdrawn
+    pub async fn withdraw_unseen(pool: &PgPool, traversal_started_at: DateTime<Utc>) -> Result<u64, ApiError> {
+        let result = sqlx::query(
+            "UPDATE osdr_items
+             SET withdrawn_at = now()
+             WHERE withdrawn_at IS NULL
+               AND (last_seen_at IS NULL OR last_seen_at < $1)"
+        )
+        .bind(traversal_started_at)
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Ревизии датасета, новые первыми; `raw` только по запросу
+    pub async fn list_versions(
+        pool: &PgPool,
+        item_id: i64,
+        include_raw: bool,
+    ) -> Result<Vec<OsdrItemVersion>, ApiError> {
+        let rows = sqlx::query_as::<_, OsdrItemVersion>(
+            "SELECT id, item_id, version, content_hash, title, status, updated_at,
+                    CASE WHEN $2 THEN raw END AS raw, diff, recorded_at
+             FROM osdr_item_versions
+             WHERE item_id = $1
+             ORDER BY version DESC"
+        )
+        .bind(item_id)
+        .bind(include_raw)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
     }
 
     /// Получить количество items
@@ -169,6 +674,422 @@ impl OsdrRepository {
     }
 }
 
+/// Чекпоинт постраничной синхронизации OSDR (одна строка `name = 'osdr'`)
+pub struct OsdrSyncRepository;
+
+impl OsdrSyncRepository {
+    const NAME: &'static str = "osdr";
+
+    pub async fn get(pool: &PgPool) -> Result<Option<OsdrSyncCheckpoint>, ApiError> {
+        let row = sqlx::query_as::<_, OsdrSyncCheckpoint>(
+            "SELECT mode, next_from, total_hits, started_at, updated_at, completed_at
+             FROM osdr_sync_checkpoint WHERE name = $1"
+        )
+        .bind(Self::NAME)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Начать новый обход с нулевого смещения; возвращает момент начала обхода
+    pub async fn begin(pool: &PgPool, mode: OsdrSyncMode) -> Result<DateTime<Utc>, ApiError> {
+        let started_at = sqlx::query_scalar::<_, DateTime<Utc>>(
+            "INSERT INTO osdr_sync_checkpoint (name, mode, next_from, total_hits, started_at, updated_at, completed_at)
+             VALUES ($1, $2, 0, NULL, now(), now(), NULL)
+             ON CONFLICT (name) DO UPDATE
+             SET mode = EXCLUDED.mode,
+                 next_from = 0,
+                 total_hits = NULL,
+                 started_at = now(),
+                 updated_at = now(),
+                 completed_at = NULL
+             RETURNING started_at"
+        )
+        .bind(Self::NAME)
+        .bind(mode.as_str())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(started_at)
+    }
+
+    /// Запомнить смещение после сохранённой страницы
+    pub async fn advance(pool: &PgPool, next_from: i64, total_hits: Option<i64>) -> Result<(), ApiError> {
+        sqlx::query(
+            "UPDATE osdr_sync_checkpoint
+             SET next_from = $2, total_hits = COALESCE($3, total_hits), updated_at = now()
+             WHERE name = $1"
+        )
+        .bind(Self::NAME)
+        .bind(next_from)
+        .bind(total_hits)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn complete(pool: &PgPool) -> Result<(), ApiError> {
+        sqlx::query(
+            "UPDATE osdr_sync_checkpoint SET completed_at = now(), updated_at = now() WHERE name = $1"
+        )
+        .bind(Self::NAME)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Журнал прогонов синхронизации
+pub struct SyncRunRepository;
+
+impl SyncRunRepository {
+    /// Открыть прогон со статусом `running`
+    pub async fn start(pool: &PgPool, source: &str, trigger: SyncTrigger, mode: &str) -> Result<i64, ApiError> {
+        let id = sqlx::query_scalar::<_, i64>(
+            "INSERT INTO sync_runs (source, trigger, mode, status, started_at)
+             VALUES ($1, $2, $3, 'running', now())
+             RETURNING id"
+        )
+        .bind(source)
+        .bind(trigger.as_str())
+        .bind(mode)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Закрыть прогон OSDR: `error` = None — succeeded, иначе failed
+    pub async fn finish_osdr(pool: &PgPool, report: &OsdrSyncReport, error: Option<&str>) -> Result<(), ApiError> {
+        sqlx::query(
+            "UPDATE sync_runs
+             SET status = CASE WHEN $2::text IS NULL THEN 'succeeded' ELSE 'failed' END,
+                 error = $2,
+                 finished_at = now(),
+                 resumed_from = $3, pages = $4, fetched = $5,
+                 inserted = $6, changed = $7, unchanged = $8, failed = $9,
+                 total_hits = $10, stopped_early = $11, completed = $12,
+                 upstream_ms_total = $13, upstream_ms_max = $14, error_samples = $15,
+                 withdrawn = $16, duplicates = $17
+             WHERE id = $1"
+        )
+        .bind(report.run_id)
+        .bind(error)
+        .bind(report.resumed_from)
+        .bind(report.pages as i32)
+        .bind(report.fetched as i64)
+        .bind(report.inserted as i64)
+        .bind(report.changed as i64)
+        .bind(report.unchanged as i64)
+        .bind(report.failed as i64)
+        .bind(report.total_hits)
+        .bind(report.stopped_early)
+        .bind(report.completed)
+        .bind(report.upstream_ms_total as i64)
+        .bind(report.upstream_ms_max as i64)
+        .bind(sqlx::types::Json(&report.error_samples))
+        .bind(report.withdrawn as i64)
+        .bind(report.duplicates as i64)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Последние прогоны источника, новые первыми
+    pub async fn list(pool: &PgPool, source: &str, status: Option<&str>, limit: i64) -> Result<Vec<SyncRun>, ApiError> {
+        let rows = sqlx::query_as::<_, SyncRun>(
+            "SELECT id, source, trigger, mode, status, started_at, finished_at, resumed_from, pages, fetched,
+                    inserted, changed, unchanged, failed, duplicates, withdrawn, total_hits, stopped_early, completed,
+                    upstream_ms_total, upstream_ms_max, error_samples, error
+             FROM sync_runs
+             WHERE source = $1 AND ($2::text IS NULL OR status = $2)
+             ORDER BY id DESC
+             LIMIT $3"
+        )
+        .bind(source)
+        .bind(status)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Когда последний раз успешно завершился полный обход (без ранней остановки)
+    pub async fn last_full_traversal(pool: &PgPool, source: &str) -> Result<Option<DateTime<Utc>>, ApiError> {
+        let finished_at = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+            "SELECT max(finished_at) FROM sync_runs
+             WHERE source = $1 AND mode = 'full' AND status = 'succeeded' AND completed AND NOT stopped_early"
+        )
+        .bind(source)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(finished_at)
+    }
+
+    pub async fn get(pool: &PgPool, source: &str, id: i64) -> Result<Option<SyncRun>, ApiError> {
+        let row = sqlx::query_as::<_, SyncRun>(
+            "SELECT id, source, trigger, mode, status, started_at, finished_at, resumed_from, pages, fetched,
+                    inserted, changed, unchanged, failed, duplicates, withdrawn, total_hits, stopped_early, completed,
+                    upstream_ms_total, upstream_ms_max, error_samples, error
+             FROM sync_runs
+             WHERE source = $1 AND id = $2"
+        )
+        .bind(source)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+}
+
+/// Retention Repository - прореживание и удаление старых записей временных рядов
+pub struct RetentionRepository;
+
+impl RetentionRepository {
+    /// Удалить до `limit` лишних записей из [from, before): в каждом интервале `bucket_secs`
+    /// (отдельно по спутнику/источнику) остаётся самая новая. Ранжируется только этот диапазон,
+    /// поэтому сервис передаёт ограниченные окна, а не весь уровень
+    pub async fn compact_batch(
+        pool: &PgPool,
+        table: RetentionTable,
+        scope: Option<&str>,
+        from: Option<DateTime<Utc>>,
+        before: DateTime<Utc>,
+        bucket_secs: u32,
+        limit: i64,
+    ) -> Result<u64, ApiError> {
+        let (name, partition) = (table.as_str(), retention_partition(table));
+        let mut qb = QueryBuilder::<Postgres>::new(format!(
+            "DELETE FROM {name} WHERE id IN (
+                SELECT id FROM (
+                    SELECT id, row_number() OVER (
+                        PARTITION BY {partition}, floor(extract(epoch FROM fetched_at) / "
+        ));
+        qb.push_bind(bucket_secs as f64)
+            .push(") ORDER BY fetched_at DESC, id DESC) AS rn FROM ")
+            .push(name)
+            .push(" WHERE fetched_at < ")
+            .push_bind(before);
+        if let Some(from) = from {
+            qb.push(" AND fetched_at >= ").push_bind(from);
+        }
+        push_retention_scope(&mut qb, table, scope);
+        qb.push(") ranked WHERE rn > 1 LIMIT ").push_bind(limit).push(")");
+
+        let result = qb.build().execute(pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Самая ранняя запись в [from, before) — начало следующего окна прореживания
+    pub async fn oldest_since(
+        pool: &PgPool,
+        table: RetentionTable,
+        scope: Option<&str>,
+        from: Option<DateTime<Utc>>,
+        before: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, ApiError> {
+        let mut qb = QueryBuilder::<Postgres>::new(format!(
+            "SELECT min(fetched_at) FROM {} WHERE fetched_at < ",
+            table.as_str()
+        ));
+        qb.push_bind(before);
+        if let Some(from) = from {
+            qb.push(" AND fetched_at >= ").push_bind(from);
+        }
+        push_retention_scope(&mut qb, table, scope);
+
+        let oldest = qb.build_query_scalar::<Option<DateTime<Utc>>>().fetch_one(pool).await?;
+        Ok(oldest)
+    }
+
+    /// Удалить до `limit` записей старше `before`; самая новая запись каждого
+    /// спутника/источника сохраняется, чтобы /last и /space/:src/latest не опустели
+    pub async fn drop_batch(
+        pool: &PgPool,
+        table: RetentionTable,
+        scope: Option<&str>,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<u64, ApiError> {
+        let (name, partition) = (table.as_str(), retention_partition(table));
+        let mut qb = QueryBuilder::<Postgres>::new(format!(
+            "DELETE FROM {name} WHERE id IN (
+                SELECT id FROM {name} t
+                WHERE EXISTS (SELECT 1 FROM {name} n WHERE n.{partition} = t.{partition} AND n.id > t.id)
+                  AND fetched_at < "
+        ));
+        qb.push_bind(before);
+        push_retention_scope(&mut qb, table, scope);
+        qb.push(" LIMIT ").push_bind(limit).push(")");
+
+        let result = qb.build().execute(pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Занять session-level advisory lock прогона на отдельном соединении;
+    /// `None` — прогон уже идёт (в этом или другом экземпляре сервиса)
+    pub async fn try_lock(pool: &PgPool) -> Result<Option<PoolConnection<Postgres>>, ApiError> {
+        let mut conn = pool.acquire().await?;
+        let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock($1)")
+            .bind(RETENTION_LOCK_ID)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok(locked.then_some(conn))
+    }
+
+    /// Освободить lock; если не вышло, соединение закрывается, чтобы lock не остался в пуле
+    pub async fn unlock(mut conn: PoolConnection<Postgres>) {
+        let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(RETENTION_LOCK_ID)
+            .execute(&mut *conn)
+            .await;
+        if unlocked.is_err() {
+            let _ = conn.close().await;
+        }
+    }
+
+    /// Источники, у которых есть снимки в space_cache
+    pub async fn space_sources(pool: &PgPool) -> Result<Vec<String>, ApiError> {
+        let rows = sqlx::query_scalar::<_, String>("SELECT DISTINCT source FROM space_cache ORDER BY source")
+            .fetch_all(pool)
+            .await?;
+
+        Ok(rows)
+    }
+
+    /// Открыть прогон со статусом `running`
+    pub async fn start(pool: &PgPool, trigger: SyncTrigger) -> Result<i64, ApiError> {
+        let id = sqlx::query_scalar::<_, i64>(
+            "INSERT INTO retention_runs (trigger, status, started_at)
+             VALUES ($1, 'running', now())
+             RETURNING id"
+        )
+        .bind(trigger.as_str())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Закрыть прогон: `error` = None — succeeded, иначе failed
+    pub async fn finish(
+        pool: &PgPool,
+        id: i64,
+        scopes: &[RetentionScopeReport],
+        error: Option<&str>,
+    ) -> Result<RetentionRun, ApiError> {
+        let row = sqlx::query_as::<_, RetentionRun>(
+            "UPDATE retention_runs
+             SET status = CASE WHEN $2::text IS NULL THEN 'succeeded' ELSE 'failed' END,
+                 error = $2,
+                 finished_at = now(),
+                 compacted = $3, dropped = $4, scopes = $5
+             WHERE id = $1
+             RETURNING id, trigger, status, started_at, finished_at, compacted, dropped, scopes, error"
+        )
+        .bind(id)
+        .bind(error)
+        .bind(scopes.iter().map(|s| s.compacted as i64).sum::<i64>())
+        .bind(scopes.iter().map(|s| s.dropped as i64).sum::<i64>())
+        .bind(sqlx::types::Json(scopes))
+        .fetch_one(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Последние прогоны, новые первыми
+    pub async fn list(pool: &PgPool, limit: i64) -> Result<Vec<RetentionRun>, ApiError> {
+        let rows = sqlx::query_as::<_, RetentionRun>(
+            "SELECT id, trigger, status, started_at, finished_at, compacted, dropped, scopes, error
+             FROM retention_runs
+             ORDER BY id DESC
+             LIMIT $1"
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+}
+
+/// Колонка, внутри которой действует политика хранения (whitelist для текста запроса)
+fn retention_partition(table: RetentionTable) -> &'static str {
+    match table {
+        RetentionTable::IssFetchLog => "norad_id",
+        RetentionTable::SpaceCache => "source",
+    }
+}
+
+/// Ограничить выборку одним источником space_cache
+fn push_retention_scope(qb: &mut QueryBuilder<'_, Postgres>, table: RetentionTable, scope: Option<&str>) {
+    if let (RetentionTable::SpaceCache, Some(source)) = (table, scope) {
+        qb.push(" AND source = ").push_bind(source.to_string());
+    }
+}
+
+/// SQL-выражение хэша содержимого (OSDR, space_cache): канонический текст jsonb (ключи упорядочены) → sha256
+pub(crate) fn content_hash_sql(param: &str) -> String {
+    format!("encode(sha256(convert_to({}::jsonb::text, 'UTF8')), 'hex')", param)
+}
+
+/// Условия фильтров списка OSDR (только bind-параметры)
+fn push_osdr_filters(qb: &mut QueryBuilder<'_, Postgres>, filter: &OsdrListFilter) {
+    if let Some(status) = &filter.status {
+        qb.push(" AND status = ").push_bind(status.clone());
+    }
+    if let Some(search) = &filter.search {
+        qb.push(" AND title ILIKE ").push_bind(format!("%{}%", escape_like(search)));
+    }
+    if !filter.include_withdrawn {
+        qb.push(" AND withdrawn_at IS NULL");
+    }
+    // `@>` по массиву находит элемент, по строке — равенство; обе формы идут через GIN-индекс
+    for facet in OsdrFacet::ALL {
+        if let Some(value) = filter.facets.text(facet) {
+            qb.push(format!(" AND {} @> to_jsonb(", osdr_facet_expr(facet)))
+                .push_bind(value.to_string())
+                .push("::text)");
+        }
+    }
+    if let Some(year) = filter.facets.release_year {
+        qb.push(format!(" AND {} = ", osdr_facet_expr(OsdrFacet::ReleaseYear)))
+            .push_bind(year);
+    }
+}
+
+/// Выражение фасета; совпадает с выражениями индексов из init_db, иначе планировщик их не возьмёт
+pub(crate) fn osdr_facet_expr(facet: OsdrFacet) -> String {
+    match facet.raw_key() {
+        Some(key) => format!("(raw->'{}')", key),
+        None => "(EXTRACT(YEAR FROM updated_at AT TIME ZONE 'UTC')::int)".to_string(),
+    }
+}
+
+/// Выражение сортировки: NULL заменяется заглушкой, чтобы порядок был полным и стабильным
+fn osdr_sort_expr(sort_by: OsdrSortBy) -> &'static str {
+    match sort_by {
+        OsdrSortBy::InsertedAt => "inserted_at",
+        OsdrSortBy::UpdatedAt => "COALESCE(updated_at, '-infinity'::timestamptz)",
+        OsdrSortBy::Title => "COALESCE(title, '')",
+        OsdrSortBy::DatasetId => "COALESCE(dataset_id, '')",
+    }
+}
+
+/// Экранировать спецсимволы LIKE (`\` — escape-символ Postgres по умолчанию)
+pub(crate) fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
 /// Cache Repository - работа с универсальным кэшем
 pub struct CacheRepository;
 
@@ -176,7 +1097,7 @@ impl CacheRepository {
     /// Получить последний кэш по источнику
     pub async fn get_latest(pool: &PgPool, source: &str) -> Result<Option<SpaceCache>, ApiError> {
         let row = sqlx::query_as::<_, SpaceCache>(
-            "SELECT id, source, fetched_at, payload
+            "SELECT id, source, fetched_at, last_seen_at, payload, schema_warning
              FROM space_cache
              WHERE source = $1
              ORDER BY id DESC LIMIT 1"
@@ -188,38 +1109,146 @@ impl CacheRepository {
         Ok(row)
     }
 
-    /// Сохранить в кэш
-    pub async fn save(pool: &PgPool, source: &str, payload: Value) -> Result<SpaceCache, ApiError> {
-        // Advisory lock для защиты от одновременных записей
-        sqlx::query("SELECT pg_advisory_lock($1)")
+    /// Сохранить в кэш. Если содержимое совпадает с последним снимком источника
+    /// (по хэшу канонического jsonb), новая строка не пишется — у снимка
+    /// только сдвигается last_seen_at
+    pub async fn save(
+        pool: &PgPool,
+        source: &str,
+        payload: Value,
+        schema_warning: Option<&str>,
+    ) -> Result<SpaceCache, ApiError> {
+        let now = Utc::now();
+        let mut tx = pool.begin().await?;
+
+        // Advisory lock для защиты от одновременных записей (снимается на commit)
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
             .bind(SPACE_CACHE_LOCK_ID)
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
 
-        let result = sqlx::query_as::<_, SpaceCache>(
-            "INSERT INTO space_cache (source, payload, fetched_at)
-             VALUES ($1, $2, $3)
-             RETURNING id, source, fetched_at, payload"
-        )
+        let unchanged = sqlx::query_as::<_, SpaceCache>(&format!(
+            "UPDATE space_cache SET last_seen_at = $3, schema_warning = $4
+             WHERE id = (SELECT id FROM space_cache WHERE source = $1 ORDER BY id DESC LIMIT 1)
+               AND content_hash = {}
+             RETURNING id, source, fetched_at, last_seen_at, payload, schema_warning",
+            content_hash_sql("$2")
+        ))
         .bind(source)
         .bind(&payload)
-        .bind(Utc::now())
-        .fetch_one(pool)
-        .await;
+        .bind(now)
+        .bind(schema_warning)
+        .fetch_optional(&mut *tx)
+        .await?;
 
-        // Освободить lock
-        let _ = sqlx::query("SELECT pg_advisory_unlock($1)")
-            .bind(SPACE_CACHE_LOCK_ID)
-            .execute(pool)
-            .await;
+        let row = match unchanged {
+            Some(row) => row,
+            None => {
+                sqlx::query_as::<_, SpaceCache>(&format!(
+                    "INSERT INTO space_cache (source, payload, fetched_at, last_seen_at, content_hash, schema_warning)
+                     VALUES ($1, $2, $3, $3, {}, $4)
+                     RETURNING id, source, fetched_at, last_seen_at, payload, schema_warning",
+                    content_hash_sql("$2")
+                ))
+                .bind(source)
+                .bind(&payload)
+                .bind(now)
+                .bind(schema_warning)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
 
-        result.map_err(ApiError::from)
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Снимок источника по id
+    pub async fn get(pool: &PgPool, source: &str, id: i64) -> Result<Option<SpaceCache>, ApiError> {
+        let row = sqlx::query_as::<_, SpaceCache>(
+            "SELECT id, source, fetched_at, last_seen_at, payload, schema_warning
+             FROM space_cache
+             WHERE source = $1 AND id = $2"
+        )
+        .bind(source)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Снимок источника, предшествующий снимку `before_id`
+    pub async fn get_previous(pool: &PgPool, source: &str, before_id: i64) -> Result<Option<SpaceCache>, ApiError> {
+        let row = sqlx::query_as::<_, SpaceCache>(
+            "SELECT id, source, fetched_at, last_seen_at, payload, schema_warning
+             FROM space_cache
+             WHERE source = $1 AND id < $2
+             ORDER BY id DESC LIMIT 1"
+        )
+        .bind(source)
+        .bind(before_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// История снимков источника, новые первыми. Keyset по (fetched_at, id) —
+    /// идёт по индексу ix_space_cache_source; payload читается только по запросу
+    pub async fn history(
+        pool: &PgPool,
+        source: &str,
+        filter: &SpaceHistoryFilter,
+        limit: i64,
+    ) -> Result<Vec<SpaceSnapshot>, ApiError> {
+        let mut qb = QueryBuilder::<Postgres>::new(format!(
+            "SELECT id, source, fetched_at, last_seen_at, pg_column_size(payload)::bigint AS payload_bytes,
+                    schema_warning, {} AS payload
+             FROM space_cache
+             WHERE source = ",
+            if filter.include_payload { "payload" } else { "NULL::jsonb" },
+        ));
+        qb.push_bind(source);
+        if let Some(from) = filter.from {
+            qb.push(" AND fetched_at >= ").push_bind(from);
+        }
+        if let Some(to) = filter.to {
+            qb.push(" AND fetched_at <= ").push_bind(to);
+        }
+        if let Some(after) = &filter.after {
+            qb.push(" AND (fetched_at, id) < (")
+                .push_bind(after.fetched_at)
+                .push(", ")
+                .push_bind(after.id)
+                .push(")");
+        }
+        qb.push(" ORDER BY fetched_at DESC, id DESC LIMIT ").push_bind(limit);
+
+        let rows = qb.build_query_as::<SpaceSnapshot>().fetch_all(pool).await?;
+        Ok(rows)
+    }
+
+    /// Снимок, актуальный на момент `ts`: последний полученный не позже него
+    pub async fn get_at(pool: &PgPool, source: &str, ts: DateTime<Utc>) -> Result<Option<SpaceCache>, ApiError> {
+        let row = sqlx::query_as::<_, SpaceCache>(
+            "SELECT id, source, fetched_at, last_seen_at, payload, schema_warning
+             FROM space_cache
+             WHERE source = $1 AND fetched_at <= $2
+             ORDER BY fetched_at DESC, id DESC LIMIT 1"
+        )
+        .bind(source)
+        .bind(ts)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
     }
 
     /// Получить все источники (для summary)
     pub async fn get_all_sources(pool: &PgPool) -> Result<Vec<SpaceCache>, ApiError> {
         let rows = sqlx::query_as::<_, SpaceCache>(
-            "SELECT DISTINCT ON (source) id, source, fetched_at, payload
+            "SELECT DISTINCT ON (source) id, source, fetched_at, last_seen_at, payload, schema_warning
              FROM space_cache
              ORDER BY source, id DESC"
         )
@@ -4,7 +4,7 @@ use axum::{
     extract::Request,
     middleware::Next,
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
     Router,
 };
 use tower::ServiceBuilder;
@@ -25,15 +25,46 @@ pub fn create_router(state: AppState, config: Config) -> Router {
         .route("/last", get(iss_last_handler))
         .route("/fetch", get(iss_fetch_handler))
         .route("/iss/trend", get(iss_trend_handler))
+        .route("/iss/history", get(iss_history_handler))
+        .route("/iss/history.geojson", get(iss_history_geojson_handler))
+        .route("/iss/history.kml", get(iss_history_kml_handler))
+        .route("/iss/position", get(iss_position_handler))
+        .route("/iss/passes", get(iss_passes_handler))
+        .route("/iss/stream", get(iss_stream_handler))
+        .route("/iss/illumination", get(iss_illumination_handler))
+        .route("/iss/terminator", get(iss_terminator_handler))
+        .route("/iss/geofences", get(geofence_list_handler).post(geofence_create_handler))
+        .route(
+            "/iss/geofences/:id",
+            get(geofence_get_handler)
+                .put(geofence_update_handler)
+                .delete(geofence_delete_handler),
+        )
+        .route("/iss/geofence/events", get(geofence_events_handler))
         
         // OSDR endpoints
         .route("/osdr/sync", get(osdr_sync_handler))
+        .route("/osdr/sync/runs", get(osdr_sync_runs_handler))
+        .route("/osdr/sync/runs/:id", get(osdr_sync_run_handler))
         .route("/osdr/list", get(osdr_list_handler))
+        .route("/osdr/facets", get(osdr_facets_handler))
+        .route("/osdr/export.csv", get(osdr_export_csv_handler))
+        .route("/osdr/export.ndjson", get(osdr_export_ndjson_handler))
+        .route("/osdr/export.parquet", get(osdr_export_parquet_handler))
+        .route("/osdr/:dataset_id", get(osdr_dataset_handler))
+        .route("/osdr/:dataset_id/history", get(osdr_history_handler))
         
         // Space cache endpoints
         .route("/space/:src/latest", get(space_latest_handler))
+        .route("/space/:src/history", get(space_history_handler))
+        .route("/space/:src/at", get(space_at_handler))
+        .route("/space/:src/diff", get(space_diff_handler))
         .route("/space/refresh", get(space_refresh_handler))
         .route("/space/summary", get(space_summary_handler))
+
+        // Admin endpoints
+        .route("/admin/retention", get(admin_retention_handler))
+        .route("/admin/retention/run", post(admin_retention_run_handler))
         
         .layer(
             ServiceBuilder::new()
@@ -1,12 +1,45 @@
 use crate::clients::ApiClient;
+use crate::config::{Config, ISS_NORAD_ID};
 use crate::domain::*;
 use crate::error::ApiError;
 use crate::repo::*;
 use crate::cache::{CacheClient, cache_keys};
-use chrono::Utc;
+use crate::orbit::{Observer, OrbitPropagator};
+use crate::solar;
+use crate::geo;
+use crate::diff;
+use crate::export::{ExportEncoder, ExportFormat};
+use chrono::{DateTime, Duration, Utc};
 use serde_json::Value;
 use sqlx::PgPool;
-use tracing::{error, info};
+use bytes::Bytes;
+use futures::Stream;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex, OwnedSemaphorePermit};
+use tracing::{debug, error, info, warn};
+
+// Размер страницы догрузки пропущенных записей при resume /iss/stream
+pub const STREAM_RESUME_LIMIT: i64 = 100;
+
+// За сколько последних минут искать входы/выходы из тени для /iss/illumination
+const ILLUMINATION_EVENTS_WINDOW_MIN: i64 = 180;
+
+// Окно /iss/trend по умолчанию, минуты
+const TREND_DEFAULT_WINDOW_MIN: i64 = 30;
+
+// Ограничения для /iss/history
+const HISTORY_MAX_SPAN_SEC: i64 = 31 * 86_400;
+const HISTORY_MIN_STEP_SEC: i64 = 60;
+const HISTORY_TARGET_POINTS: i64 = 500;
+const HISTORY_MAX_POINTS: i64 = 5_000;
+
+// Защита tombstone от усечённой выдачи OSDR: hits.total ниже этой доли активных записей
+// или больше этой доли кандидатов в withdrawn — пометку пропускаем
+const OSDR_WITHDRAW_MIN_HITS_RATIO: f64 = 0.9;
+const OSDR_WITHDRAW_MAX_SHARE: f64 = 0.1;
+
+// Сколько последних прогонов retention показывать в /admin/retention
+const RETENTION_RUNS_SHOWN: i64 = 20;
 
 /// ISS Service - бизнес-логика для МКС
 #[derive(Clone)]
@@ -21,137 +54,570 @@ impl IssService {
         Self { pool, client, cache }
     }
 
-    /// Получить последние данные МКС
-    pub async fn get_last(&self) -> Result<Option<IssFetchLog>, ApiError> {
+    /// Опрашивает ли планировщик этот спутник
+    pub fn is_tracked(&self, norad_id: i32) -> bool {
+        self.client.config().is_tracked(norad_id)
+    }
+
+    /// Получить последние данные по спутнику
+    pub async fn get_last(&self, norad_id: i32) -> Result<Option<IssFetchLog>, ApiError> {
         // Проверяем кэш
-        if let Ok(Some(cached)) = self.cache.get::<IssFetchLog>(cache_keys::iss_latest()).await {
+        let cache_key = cache_keys::iss_latest(norad_id);
+        if let Ok(Some(cached)) = self.cache.get::<IssFetchLog>(&cache_key).await {
             return Ok(Some(cached));
         }
         
         // Если нет в кэше, получаем из БД
-        let result = IssRepository::get_last(&self.pool).await?;
+        let result = IssRepository::get_last(&self.pool, norad_id).await?;
         
         // Сохраняем в кэш
         if let Some(ref log) = result {
-            let _ = self.cache.set(cache_keys::iss_latest(), log, Some(300)).await;
+            let _ = self.cache.set(&cache_key, log, Some(300)).await;
         }
         
         Ok(result)
     }
 
-    /// Получить тренд движения МКС
-    pub async fn get_trend(&self) -> Result<IssTrend, ApiError> {
+    /// Получить тренд движения МКС по всем замерам за последние window_min минут
+    pub async fn get_trend(&self, norad_id: i32, window_min: Option<i64>) -> Result<IssTrend, ApiError> {
+        let window_min = window_min.unwrap_or(TREND_DEFAULT_WINDOW_MIN);
+
         // Проверяем кэш
-        if let Ok(Some(cached)) = self.cache.get::<IssTrend>(cache_keys::iss_trend()).await {
+        let cache_key = cache_keys::iss_trend(norad_id, window_min);
+        if let Ok(Some(cached)) = self.cache.get::<IssTrend>(&cache_key).await {
             return Ok(cached);
         }
         
-        let rows = IssRepository::get_trend(&self.pool).await?;
+        let window = Duration::minutes(window_min);
+        let rows = IssRepository::get_since(&self.pool, norad_id, Utc::now() - window).await?;
+        let expected_interval = self.client.config().iss_every_seconds as f64;
+        let trend = Self::compute_trend(&rows, window.num_seconds(), expected_interval);
+        
+        // Сохраняем в кэш
+        let _ = self.cache.set(&cache_key, &trend, Some(60)).await;
+        
+        Ok(trend)
+    }
 
-        if rows.len() < 2 {
-            return Ok(IssTrend::empty());
+    /// Посчитать тренд по упорядоченным по времени замерам.
+    /// Отрезки длиннее двух интервалов опроса считаются пропусками и не входят
+    /// в расчёт пути и скорости — дуга через пропуск не соответствует орбите.
+    pub(crate) fn compute_trend(rows: &[IssFetchLog], window_sec: i64, expected_interval_sec: f64) -> IssTrend {
+        struct Sample {
+            at: DateTime<Utc>,
+            lat: f64,
+            lon: f64,
+            altitude: Option<f64>,
+            velocity: Option<f64>,
         }
 
-        let to = &rows[0];
-        let from = &rows[1];
+        let samples: Vec<Sample> = rows
+            .iter()
+            .map(|r| Sample {
+                at: r.fetched_at,
+                lat: r.position.latitude,
+                lon: r.position.longitude,
+                altitude: r.position.altitude,
+                velocity: r.position.velocity,
+            })
+            .collect();
 
-        let from_lat = Self::extract_f64(&from.payload, "latitude");
-        let from_lon = Self::extract_f64(&from.payload, "longitude");
-        let to_lat = Self::extract_f64(&to.payload, "latitude");
-        let to_lon = Self::extract_f64(&to.payload, "longitude");
-        let velocity = Self::extract_f64(&to.payload, "velocity");
+        if samples.len() < 2 {
+            return IssTrend::empty();
+        }
 
-        let mut delta_km = 0.0;
-        let mut movement = false;
+        let gap_threshold = expected_interval_sec * 2.0;
+        let mut ground_distance_km = 0.0;
+        let mut moving_sec = 0.0;
+        let mut max_speed: Option<f64> = None;
+        let mut heading = None;
+        let mut gaps = Vec::new();
 
-        if let (Some(lat1), Some(lon1), Some(lat2), Some(lon2)) = (from_lat, from_lon, to_lat, to_lon) {
-            delta_km = Self::haversine_km(lat1, lon1, lat2, lon2);
-            movement = delta_km > 0.1;
+        for pair in samples.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let dt = (b.at - a.at).num_milliseconds() as f64 / 1000.0;
+            if dt <= 0.0 {
+                continue;
+            }
+            if dt > gap_threshold {
+                gaps.push(IssSampleGap { from: a.at, to: b.at, dt_sec: dt });
+                continue;
+            }
+            let dist = geo::haversine_km(a.lat, a.lon, b.lat, b.lon);
+            let speed = dist / dt * 3600.0;
+            ground_distance_km += dist;
+            moving_sec += dt;
+            max_speed = Some(max_speed.map_or(speed, |m: f64| m.max(speed)));
+            heading = Some(Self::bearing_deg(a.lat, a.lon, b.lat, b.lon));
         }
 
-        let dt_sec = (to.fetched_at - from.fetched_at).num_milliseconds() as f64 / 1000.0;
+        let altitudes: Vec<f64> = samples.iter().filter_map(|s| s.altitude).collect();
+        let altitude_mean_km = if altitudes.is_empty() {
+            None
+        } else {
+            Some(altitudes.iter().sum::<f64>() / altitudes.len() as f64)
+        };
 
-        let trend = IssTrend {
-            movement,
+        let first = &samples[0];
+        let last = &samples[samples.len() - 1];
+        let delta_km = geo::haversine_km(first.lat, first.lon, last.lat, last.lon);
+
+        IssTrend {
+            movement: ground_distance_km > 0.1,
             delta_km,
-            dt_sec,
-            velocity_kmh: velocity,
-            from_time: Some(from.fetched_at),
-            to_time: Some(to.fetched_at),
-            from_lat,
-            from_lon,
-            to_lat,
-            to_lon,
+            dt_sec: (last.at - first.at).num_milliseconds() as f64 / 1000.0,
+            velocity_kmh: last.velocity,
+            from_time: Some(first.at),
+            to_time: Some(last.at),
+            from_lat: Some(first.lat),
+            from_lon: Some(first.lon),
+            to_lat: Some(last.lat),
+            to_lon: Some(last.lon),
+            stats: Some(IssTrendStats {
+                window_sec,
+                samples: samples.len(),
+                ground_distance_km,
+                avg_ground_speed_kmh: (moving_sec > 0.0).then(|| ground_distance_km / moving_sec * 3600.0),
+                max_ground_speed_kmh: max_speed,
+                altitude_min_km: altitudes.iter().copied().reduce(f64::min),
+                altitude_max_km: altitudes.iter().copied().reduce(f64::max),
+                altitude_mean_km,
+                heading_deg: heading,
+                gaps,
+            }),
+        }
+    }
+
+    /// Получить трек спутника за интервал с даунсэмплингом по времени
+    pub async fn get_history(
+        &self,
+        norad_id: i32,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        step_sec: Option<i64>,
+    ) -> Result<IssHistory, ApiError> {
+        let now = Utc::now();
+        let to = to.unwrap_or(now);
+        let from = from.unwrap_or(to - Duration::hours(24));
+        if from >= to {
+            return Err(ApiError::bad_request("VALIDATION_ERROR", "`from` must be earlier than `to`"));
+        }
+
+        let span_sec = (to - from).num_seconds();
+        if span_sec > HISTORY_MAX_SPAN_SEC {
+            return Err(ApiError::bad_request(
+                "VALIDATION_ERROR",
+                format!("Range is too large (max {} days)", HISTORY_MAX_SPAN_SEC / 86_400),
+            ));
+        }
+
+        let step = Self::history_step(span_sec, step_sec)?;
+        let (from, to) = Self::align_range(from, to, step);
+
+        // Проверяем кэш
+        let cache_key = cache_keys::iss_history(norad_id, from.timestamp(), to.timestamp(), step);
+        if let Ok(Some(cached)) = self.cache.get::<IssHistory>(&cache_key).await {
+            return Ok(cached);
+        }
+
+        let mut points = IssRepository::get_history(&self.pool, norad_id, from, to, step).await?;
+
+        // Бакеты без замеров заполняем SGP4-пропагацией (если есть свежий TLE этого спутника)
+        match self.propagator().await {
+            Ok(Some(propagator)) if propagator.norad_id() == norad_id as u64 => {
+                Self::fill_gaps(&mut points, &propagator, from, to.min(now), step);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("ISS history gap filling skipped: {}", e),
+        }
+
+        for p in points.iter_mut() {
+            p.illumination = p.altitude.map(|alt| solar::illumination(p.latitude, p.longitude, alt, p.fetched_at));
+        }
+        // События тени — только по реальным замерам, не по рассчитанным точкам
+        let samples: Vec<_> = points
+            .iter()
+            .filter(|p| !p.propagated)
+            .filter_map(|p| Some((p.fetched_at, p.latitude, p.longitude, p.altitude?)))
+            .collect();
+        let eclipses = solar::eclipse_events(&samples);
+
+        let history = IssHistory { norad_id, from, to, step_sec: step, points, eclipses };
+
+        // Закрытый интервал в прошлом уже не изменится — кэшируем надолго
+        let ttl = if to <= now { 3600 } else { 60 };
+        let _ = self.cache.set(&cache_key, &history, Some(ttl)).await;
+
+        Ok(history)
+    }
+
+    /// Освещённость последнего положения спутника и недавние входы/выходы из тени
+    pub async fn get_illumination(&self, norad_id: i32) -> Result<IssIllumination, ApiError> {
+        let cache_key = cache_keys::iss_illumination(norad_id);
+        if let Ok(Some(cached)) = self.cache.get::<IssIllumination>(&cache_key).await {
+            return Ok(cached);
+        }
+
+        let last = self
+            .get_last(norad_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found("No position data yet"))?;
+        let pos = &last.position;
+        let altitude = pos
+            .altitude
+            .ok_or_else(|| ApiError::not_found("Latest sample has no altitude"))?;
+        let at = pos.timestamp.unwrap_or(last.fetched_at);
+
+        let since = Utc::now() - Duration::minutes(ILLUMINATION_EVENTS_WINDOW_MIN);
+        let rows = IssRepository::get_since(&self.pool, norad_id, since).await?;
+        let samples: Vec<_> = rows
+            .iter()
+            .filter_map(|r| {
+                let p = &r.position;
+                Some((p.timestamp.unwrap_or(r.fetched_at), p.latitude, p.longitude, p.altitude?))
+            })
+            .collect();
+
+        let (subsolar_lat, subsolar_lon) = solar::subsolar_point(at);
+        let result = IssIllumination {
+            norad_id,
+            at,
+            latitude: pos.latitude,
+            longitude: pos.longitude,
+            altitude,
+            state: solar::illumination(pos.latitude, pos.longitude, altitude, at),
+            upstream_visibility: pos.visibility.clone(),
+            subsolar_lat,
+            subsolar_lon,
+            eclipses: solar::eclipse_events(&samples),
         };
-        
-        // Сохраняем в кэш
-        let _ = self.cache.set(cache_keys::iss_trend(), &trend, Some(60)).await;
-        
-        Ok(trend)
+
+        let _ = self.cache.set(&cache_key, &result, Some(60)).await;
+
+        Ok(result)
+    }
+
+    /// Положение МКС в произвольный момент (прошлое или будущее) по последнему TLE
+    pub async fn get_position(&self, at: Option<DateTime<Utc>>) -> Result<OrbitPosition, ApiError> {
+        let propagator = self
+            .propagator()
+            .await?
+            .ok_or_else(|| ApiError::not_found("No TLE data available yet"))?;
+
+        propagator.position_at(at.unwrap_or_else(Utc::now))
     }
 
-    /// Fetch ISS данные и сохранить
-    pub async fn fetch_and_save(&self) -> Result<IssFetchLog, ApiError> {
-        let payload = self.client.fetch_iss().await?;
-        let source_url = self.client.config().where_iss_url.clone();
+    /// Прогноз пролётов МКС над наблюдателем на ближайшие `days` суток
+    pub async fn get_passes(&self, lat: f64, lon: f64, alt_m: f64, days: i64) -> Result<IssPasses, ApiError> {
+        // Округляем до 0.1° (~11 км) и 100 м: на прогноз это почти не влияет,
+        // а кэш становится общим для соседних пользователей
+        let lat = (lat * 10.0).round() / 10.0;
+        let lon = (lon * 10.0).round() / 10.0;
+        let alt_m = (alt_m / 100.0).round() * 100.0;
+        let now = Utc::now();
+
+        let cache_key = cache_keys::iss_passes(lat, lon, alt_m, days);
+        if let Ok(Some(mut cached)) = self.cache.get::<IssPasses>(&cache_key).await {
+            // Прогноз из кэша мог устареть на пару пролётов — отбрасываем прошедшие
+            cached.passes.retain(|p| p.set.time > now);
+            return Ok(cached);
+        }
+
+        let propagator = self
+            .propagator()
+            .await?
+            .ok_or_else(|| ApiError::not_found("No TLE data available yet"))?;
+
+        let to = now + Duration::days(days);
+        if !propagator.covers(now) || !propagator.covers(to) {
+            return Err(ApiError::upstream_error("TLE is too old for pass prediction"));
+        }
+
+        let observer = Observer::new(lat, lon, alt_m / 1000.0);
+        let tle_epoch = propagator.epoch();
+        // Перебор по шагу на несколько суток — CPU-bound, не держим на нём рантайм
+        let passes = tokio::task::spawn_blocking(move || propagator.find_passes(&observer, now, to))
+            .await
+            .map_err(|e| ApiError::internal_error(format!("Pass prediction failed: {}", e)))??;
+        let result = IssPasses {
+            lat,
+            lon,
+            alt_m,
+            from: now,
+            to,
+            tle_epoch,
+            passes,
+        };
+
+        let _ = self.cache.set(&cache_key, &result, Some(1800)).await;
+
+        Ok(result)
+    }
+
+    /// Пропагатор по последнему сохранённому TLE
+    async fn propagator(&self) -> Result<Option<OrbitPropagator>, ApiError> {
+        let cache_key = cache_keys::space_latest("tle");
+        let tle = match self.cache.get::<SpaceCache>(&cache_key).await {
+            Ok(Some(cached)) => Some(cached),
+            _ => {
+                let result = CacheRepository::get_latest(&self.pool, "tle").await?;
+                if let Some(ref tle) = result {
+                    let _ = self.cache.set(&cache_key, tle, Some(300)).await;
+                }
+                result
+            }
+        };
+
+        tle.map(|t| OrbitPropagator::from_payload(&t.payload)).transpose()
+    }
+
+    /// Добавить рассчитанные точки для пустых бакетов в [from, to)
+    pub(crate) fn fill_gaps(
+        points: &mut Vec<IssHistoryPoint>,
+        propagator: &OrbitPropagator,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        step: i64,
+    ) {
+        let occupied: std::collections::HashSet<i64> = points
+            .iter()
+            .map(|p| p.fetched_at.timestamp().div_euclid(step))
+            .collect();
+
+        let mut bucket_ts = from.timestamp();
+        while bucket_ts < to.timestamp() {
+            let at = DateTime::from_timestamp(bucket_ts, 0).unwrap_or(from);
+            if !occupied.contains(&bucket_ts.div_euclid(step)) && propagator.covers(at) {
+                if let Ok(pos) = propagator.position_at(at) {
+                    points.push(IssHistoryPoint {
+                        id: 0,
+                        fetched_at: at,
+                        latitude: pos.latitude,
+                        longitude: pos.longitude,
+                        altitude: Some(pos.altitude),
+                        velocity: Some(pos.velocity),
+                        samples: 0,
+                        propagated: true,
+                        illumination: None,
+                    });
+                }
+            }
+            bucket_ts += step;
+        }
 
-        let log = IssRepository::save(&self.pool, &source_url, payload).await?;
+        points.sort_by_key(|p| p.fetched_at);
+    }
+
+    /// Страница записей, сохранённых после after_id (resume SSE по Last-Event-ID);
+    /// полная страница означает, что за ней могут быть ещё записи
+    pub async fn get_after(&self, norad_id: i32, after_id: i64) -> Result<Vec<IssFetchLog>, ApiError> {
+        IssRepository::get_after_id(&self.pool, norad_id, after_id, STREAM_RESUME_LIMIT).await
+    }
+
+    /// Fetch данные спутника и сохранить
+    pub async fn fetch_and_save(&self, norad_id: i32) -> Result<IssFetchLog, ApiError> {
+        let payload = self.client.fetch_iss(norad_id).await?;
+        let source_url = self.client.config().satellite_url(norad_id);
+
+        // Битый ответ источника не сохраняем: разбираем один раз здесь, а не на каждом запросе
+        let position = Self::parse_position(norad_id, &payload).map_err(|reason| {
+            warn!("Rejected satellite {} payload ({}): {}", norad_id, reason, payload);
+            ApiError::upstream_error(format!("Malformed satellite payload: {}", reason))
+        })?;
+
+        let log = IssRepository::save(&self.pool, norad_id, &source_url, payload, &position).await?;
         
         // Инвалидируем кэш
-        let _ = self.cache.delete(cache_keys::iss_latest()).await;
-        let _ = self.cache.delete(cache_keys::iss_trend()).await;
+        let _ = self.cache.delete(&cache_keys::iss_latest(norad_id)).await;
+        let _ = self.cache.delete(&cache_keys::iss_illumination(norad_id)).await;
+        let _ = self.cache.invalidate_prefix(&cache_keys::iss_trend_prefix(norad_id)).await;
         
         Ok(log)
     }
 
-    fn extract_f64(value: &Value, key: &str) -> Option<f64> {
-        if let Some(v) = value.get(key) {
-            if let Some(f) = v.as_f64() {
-                return Some(f);
-            }
-            if let Some(s) = v.as_str() {
-                return s.parse::<f64>().ok();
+    /// Разобрать телеметрию и убедиться, что источник вернул тот спутник, который запрашивали
+    pub(crate) fn parse_position(norad_id: i32, payload: &Value) -> Result<IssPosition, String> {
+        if let Some(id) = payload.get("id").and_then(|v| v.as_i64()) {
+            if id != norad_id as i64 {
+                return Err(format!("expected satellite {}, got {}", norad_id, id));
             }
         }
-        None
+        IssPosition::from_payload(payload)
     }
 
-    fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    /// Начальный азимут движения из точки 1 в точку 2, градусы [0, 360)
+    pub(crate) fn bearing_deg(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
         let rlat1 = lat1.to_radians();
         let rlat2 = lat2.to_radians();
-        let dlat = (lat2 - lat1).to_radians();
         let dlon = (lon2 - lon1).to_radians();
-        let a = (dlat / 2.0).sin().powi(2) + rlat1.cos() * rlat2.cos() * (dlon / 2.0).sin().powi(2);
-        let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-        6371.0 * c
+        let y = dlon.sin() * rlat2.cos();
+        let x = rlat1.cos() * rlat2.sin() - rlat1.sin() * rlat2.cos() * dlon.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Шаг бакета: явный (с проверкой числа точек) или подобранный под HISTORY_TARGET_POINTS
+    pub(crate) fn history_step(span_sec: i64, requested: Option<i64>) -> Result<i64, ApiError> {
+        match requested {
+            Some(step) => {
+                if span_sec / step > HISTORY_MAX_POINTS {
+                    return Err(ApiError::bad_request(
+                        "VALIDATION_ERROR",
+                        format!("`step` is too small for this range (max {} points)", HISTORY_MAX_POINTS),
+                    ));
+                }
+                Ok(step)
+            }
+            None => {
+                let auto = (span_sec + HISTORY_TARGET_POINTS - 1) / HISTORY_TARGET_POINTS;
+                Ok(auto.max(HISTORY_MIN_STEP_SEC))
+            }
+        }
+    }
+
+    /// Выровнять границы по сетке бакетов, чтобы ключи кэша были стабильными
+    pub(crate) fn align_range(from: DateTime<Utc>, to: DateTime<Utc>, step: i64) -> (DateTime<Utc>, DateTime<Utc>) {
+        let from_ts = from.timestamp().div_euclid(step) * step;
+        let to_ts = (to.timestamp() + step - 1).div_euclid(step) * step;
+        (
+            DateTime::from_timestamp(from_ts, 0).unwrap_or(from),
+            DateTime::from_timestamp(to_ts, 0).unwrap_or(to),
+        )
     }
 }
 
+/// Geofence Service - геозоны и оповещения о пролёте над ними
+#[derive(Clone)]
+pub struct GeofenceService {
+    pool: PgPool,
+}
+
+impl GeofenceService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn list(&self) -> Result<Vec<Geofence>, ApiError> {
+        GeofenceRepository::list(&self.pool).await
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Geofence, ApiError> {
+        GeofenceRepository::get(&self.pool, id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Geofence {} not found", id)))
+    }
+
+    pub async fn create(&self, name: &str, norad_id: i32, shape: &GeofenceShape, enabled: bool) -> Result<Geofence, ApiError> {
+        GeofenceRepository::create(&self.pool, name, norad_id, shape, enabled).await
+    }
+
+    pub async fn update(
+        &self,
+        id: i64,
+        name: &str,
+        norad_id: i32,
+        shape: &GeofenceShape,
+        enabled: bool,
+    ) -> Result<Geofence, ApiError> {
+        GeofenceRepository::update(&self.pool, id, name, norad_id, shape, enabled)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Geofence {} not found", id)))
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<(), ApiError> {
+        if GeofenceRepository::delete(&self.pool, id).await? {
+            Ok(())
+        } else {
+            Err(ApiError::not_found(format!("Geofence {} not found", id)))
+        }
+    }
+
+    /// Проверить новый замер против геозон спутника и записать входы/выходы
+    pub async fn evaluate(&self, log: &IssFetchLog) -> Result<usize, ApiError> {
+        let fences = GeofenceRepository::list_enabled(&self.pool, log.norad_id).await?;
+        let (lat, lon) = (log.position.latitude, log.position.longitude);
+
+        let mut recorded = 0;
+        for fence in fences {
+            let inside = geo::shape_contains(&fence.shape, lat, lon);
+            if inside == fence.inside {
+                continue;
+            }
+            if GeofenceRepository::record_transition(&self.pool, fence.id, inside, log).await? {
+                info!(
+                    "Satellite {} {} geofence '{}' at {:.3}, {:.3}",
+                    log.norad_id,
+                    if inside { "entered" } else { "left" },
+                    fence.name,
+                    lat,
+                    lon
+                );
+                recorded += 1;
+            }
+        }
+        Ok(recorded)
+    }
+
+    pub async fn events(
+        &self,
+        geofence_id: Option<i64>,
+        norad_id: Option<i32>,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<GeofenceEvent>, ApiError> {
+        GeofenceRepository::list_events(&self.pool, geofence_id, norad_id, since, limit).await
+    }
+}
+
+/// Сколько прочитанных строк выгрузки может ждать кодировщика
+const OSDR_EXPORT_BUFFER_ROWS: usize = 256;
+
+/// Значение `sync_runs.source` для прогонов OSDR
+const OSDR_SYNC_SOURCE: &str = "osdr";
+
 /// OSDR Service - бизнес-логика для NASA OSDR
 #[derive(Clone)]
 pub struct OsdrService {
     pool: PgPool,
     client: ApiClient,
     cache: CacheClient,
+    /// Фоновая и ручная синхронизация не должны идти одновременно (общий чекпоинт)
+    sync_lock: Arc<Mutex<()>>,
 }
 
 impl OsdrService {
     pub fn new(pool: PgPool, client: ApiClient, cache: CacheClient) -> Self {
-        Self { pool, client, cache }
+        Self {
+            pool,
+            client,
+            cache,
+            sync_lock: Arc::new(Mutex::new(())),
+        }
     }
 
     /// Получить список items
-    pub async fn list(&self, limit: Option<i64>) -> Result<Vec<OsdrItem>, ApiError> {
-        let limit = limit.unwrap_or(20).min(100).max(1);
-        
+    pub async fn list(&self, filter: &OsdrListFilter, include_total: bool) -> Result<OsdrPage, ApiError> {
         // Проверяем кэш
-        let cache_key = cache_keys::osdr_list(limit);
-        if let Ok(Some(cached)) = self.cache.get::<Vec<OsdrItem>>(&cache_key).await {
+        let cache_key = cache_keys::osdr_list(filter, include_total);
+        if let Ok(Some(cached)) = self.cache.get::<OsdrPage>(&cache_key).await {
             return Ok(cached);
         }
         
-        // Если нет в кэше, получаем из БД
-        let result = OsdrRepository::list(&self.pool, limit).await?;
+        // Если нет в кэше, получаем из БД; лишняя строка — признак следующей страницы
+        let mut items = OsdrRepository::list(&self.pool, filter, filter.limit + 1).await?;
+        let next_cursor = if items.len() as i64 > filter.limit {
+            items.truncate(filter.limit as usize);
+            items
+                .last()
+                .map(|last| OsdrCursor::after_item(last, filter.sort_by, filter.descending).encode())
+        } else {
+            None
+        };
+        let total = if include_total {
+            Some(OsdrRepository::count_matching(&self.pool, filter).await?)
+        } else {
+            None
+        };
+        let result = OsdrPage { items, next_cursor, total };
         
         // Сохраняем в кэш
         let _ = self.cache.set(&cache_key, &result, Some(600)).await;
@@ -159,63 +625,466 @@ impl OsdrService {
         Ok(result)
     }
 
-    /// Синхронизировать с внешним API
-    pub async fn sync(&self) -> Result<usize, ApiError> {
-        let json = self.client.fetch_osdr().await?;
-        let items = self.parse_items_array(json);
+    /// Агрегаты по фасетам под фильтрами списка; `filter.limit` — сколько значений на фасет
+    pub async fn facets(&self, filter: &OsdrListFilter) -> Result<OsdrFacets, ApiError> {
+        let cache_key = cache_keys::osdr_facets(filter);
+        if let Ok(Some(cached)) = self.cache.get::<OsdrFacets>(&cache_key).await {
+            return Ok(cached);
+        }
+
+        let count = |facet| OsdrRepository::facet_counts(&self.pool, facet, filter, filter.limit);
+        let facets = OsdrFacets {
+            total: OsdrRepository::count_matching(&self.pool, filter).await?,
+            organism: count(OsdrFacet::Organism).await?,
+            project_type: count(OsdrFacet::ProjectType).await?,
+            assay_technology: count(OsdrFacet::AssayTechnology).await?,
+            release_year: count(OsdrFacet::ReleaseYear).await?,
+        };
 
-        let mut written = 0usize;
-        for item in items {
-            if let Err(e) = self.save_item(item).await {
-                error!("Failed to save OSDR item: {}", e);
-                continue;
+        let _ = self.cache.set(&cache_key, &facets, Some(600)).await;
+
+        Ok(facets)
+    }
+
+    /// Потоковая выгрузка каталога: строки читаются из БД в фоне и кодируются по мере прихода.
+    /// Ошибка посреди выгрузки обрывает тело ответа. `permit` держится, пока занято соединение БД.
+    pub fn export(
+        &self,
+        filter: OsdrListFilter,
+        format: ExportFormat,
+        permit: OwnedSemaphorePermit,
+    ) -> Result<impl Stream<Item = std::io::Result<Bytes>> + Send + 'static, ApiError> {
+        let encoder = ExportEncoder::new(format)
+            .map_err(|e| ApiError::internal_error(format!("Export encoder failed: {}", e)))?;
+
+        let (tx, rx) = mpsc::channel(OSDR_EXPORT_BUFFER_ROWS);
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Err(e) = OsdrRepository::export_rows(&pool, &filter, &tx).await {
+                error!("OSDR export failed: {}", e);
+                let _ = tx.send(Err(e.to_string())).await;
+            }
+        });
+
+        Ok(futures::stream::unfold((rx, Some(encoder)), |(mut rx, mut encoder)| async move {
+            loop {
+                let enc = encoder.as_mut()?;
+                let chunk = match rx.recv().await {
+                    Some(Ok(item)) => match enc.push(item) {
+                        Ok(None) => continue,
+                        other => other.transpose()?,
+                    },
+                    Some(Err(e)) => {
+                        encoder = None;
+                        Err(std::io::Error::other(e))
+                    }
+                    None => encoder.take()?.finish().transpose()?,
+                };
+                if chunk.is_err() {
+                    encoder = None;
+                }
+                return Some((chunk, (rx, encoder)));
             }
-            written += 1;
+        }))
+    }
+
+    /// Карточка датасета по dataset_id
+    pub async fn get_dataset(&self, dataset_id: &str) -> Result<OsdrDatasetDetail, ApiError> {
+        let cache_key = cache_keys::osdr_item(dataset_id);
+        if let Ok(Some(cached)) = self.cache.get::<OsdrDatasetDetail>(&cache_key).await {
+            return Ok(cached);
         }
 
-        info!("OSDR sync completed: {} items written", written);
-        
-        // Инвалидируем кэш OSDR
-        let _ = self.cache.invalidate_prefix(cache_keys::osdr_prefix()).await;
-        let _ = self.cache.delete(cache_keys::osdr_count()).await;
-        
-        Ok(written)
+        let item = OsdrRepository::get_by_dataset_id(&self.pool, dataset_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Dataset {} not found", dataset_id)))?;
+        let detail = OsdrDatasetDetail {
+            normalized: Self::normalize(&item.raw),
+            item,
+        };
+
+        let _ = self.cache.set(&cache_key, &detail, Some(600)).await;
+
+        Ok(detail)
     }
 
-    async fn save_item(&self, item: Value) -> Result<(), ApiError> {
-        // Extract fields from OSDR Search API _source
-        let id = Self::pick_string(&item, &[
+    /// Ревизии датасета (только записанные при изменении хэша содержимого)
+    pub async fn get_history(&self, dataset_id: &str, include_raw: bool) -> Result<OsdrItemHistory, ApiError> {
+        let cache_key = cache_keys::osdr_history(dataset_id, include_raw);
+        if let Ok(Some(cached)) = self.cache.get::<OsdrItemHistory>(&cache_key).await {
+            return Ok(cached);
+        }
+
+        let item = OsdrRepository::get_by_dataset_id(&self.pool, dataset_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Dataset {} not found", dataset_id)))?;
+        let history = OsdrItemHistory {
+            dataset_id: dataset_id.to_string(),
+            item_id: item.id,
+            versions: OsdrRepository::list_versions(&self.pool, item.id, include_raw).await?,
+        };
+
+        let _ = self.cache.set(&cache_key, &history, Some(600)).await;
+
+        Ok(history)
+    }
+
+    /// Извлечь нормализованные поля из `_source` OSDR Search API.
+    /// Поля бывают строкой, списком строк или объектом с `Name`, поэтому всё сводится к спискам.
+    pub(crate) fn normalize(raw: &Value) -> OsdrNormalized {
+        let mission = Self::pick_strings(raw, &["Mission", "Flight Program", "mission"]).into_iter().next();
+        let doi = Self::pick_strings(raw, &["DOI", "Study DOI", "Study Publication DOI", "doi"])
+            .into_iter()
+            .next()
+            .map(|d| {
+                d.trim_start_matches("https://doi.org/")
+                    .trim_start_matches("http://dx.doi.org/")
+                    .trim_start_matches("doi:")
+                    .to_string()
+            });
+        let release_date = ["Study Public Release Date", "release_date", "Study Release Date"]
+            .iter()
+            .filter_map(|k| raw.get(k))
+            .find_map(Self::loose_datetime);
+        let file_count = ["Study File Count", "file_count", "files"]
+            .iter()
+            .filter_map(|k| raw.get(k))
+            .find_map(|v| match v {
+                Value::Array(files) => Some(files.len() as i64),
+                Value::String(s) => s.trim().parse().ok(),
+                other => other.as_i64(),
+            });
+
+        OsdrNormalized {
+            organisms: Self::pick_strings(raw, &["Organism", "organism", "organisms"]),
+            assay_types: Self::pick_strings(
+                raw,
+                &["Study Assay Technology Type", "Study Assay Measurement Type", "assay_type", "assay_types"],
+            ),
+            factors: Self::pick_strings(raw, &["Study Factor Name", "Study Factor Type", "factors"]),
+            mission,
+            release_date,
+            doi,
+            file_count,
+        }
+    }
+
+    /// Все строковые значения по ключам без повторов (строки, массивы, объекты с Name)
+    fn pick_strings(value: &Value, keys: &[&str]) -> Vec<String> {
+        fn collect(v: &Value, out: &mut Vec<String>) {
+            match v {
+                Value::String(s) => {
+                    for part in s.split(['|', ';']) {
+                        let part = part.trim();
+                        if !part.is_empty() && !out.iter().any(|x| x == part) {
+                            out.push(part.to_string());
+                        }
+                    }
+                }
+                Value::Array(items) => items.iter().for_each(|i| collect(i, out)),
+                Value::Object(map) => {
+                    if let Some(name) = map.get("Name").or_else(|| map.get("name")) {
+                        collect(name, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut out = Vec::new();
+        for k in keys {
+            if let Some(v) = value.get(k) {
+                collect(v, &mut out);
+            }
+        }
+        out
+    }
+
+    /// Дата из RFC 3339, `YYYY-MM-DD` или unix-времени (секунды)
+    fn loose_datetime(value: &Value) -> Option<DateTime<Utc>> {
+        match value {
+            Value::Number(n) => n.as_i64().and_then(|ts| DateTime::from_timestamp(ts, 0)),
+            Value::String(s) => {
+                let s = s.trim();
+                s.parse::<DateTime<Utc>>()
+                    .ok()
+                    .or_else(|| {
+                        chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                            .ok()
+                            .and_then(|d| d.and_hms_opt(0, 0, 0))
+                            .map(|dt| dt.and_utc())
+                    })
+                    .or_else(|| s.parse::<i64>().ok().and_then(|ts| DateTime::from_timestamp(ts, 0)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Синхронизировать с внешним API постранично, записав прогон в `sync_runs`.
+    /// Незавершённый прогон продолжается с чекпоинта; `None` — режим из конфига,
+    /// но не реже `OSDR_FULL_SYNC_EVERY_SECONDS` выполняется полный обход.
+    pub async fn sync(&self, mode: Option<OsdrSyncMode>, trigger: SyncTrigger) -> Result<OsdrSyncReport, ApiError> {
+        let _guard = self.sync_lock.lock().await;
+        let mode = match mode {
+            Some(mode) => mode,
+            None => self.default_mode().await?,
+        };
+
+        let run_id = SyncRunRepository::start(&self.pool, OSDR_SYNC_SOURCE, trigger, mode.as_str()).await?;
+        let mut report = OsdrSyncReport::new(run_id, mode);
+        let result = self.sync_pages(&mut report).await;
+
+        // Инвалидируем кэш OSDR, даже если прогон прервался на середине
+        if report.inserted + report.changed + report.withdrawn > 0 {
+            let _ = self.cache.invalidate_prefix(cache_keys::osdr_prefix()).await;
+            let _ = self.cache.delete(cache_keys::osdr_count()).await;
+        }
+
+        let run_error = result.as_ref().err().map(|e| e.to_string());
+        if let Err(e) = SyncRunRepository::finish_osdr(&self.pool, &report, run_error.as_deref()).await {
+            error!("Failed to record OSDR sync run {}: {}", run_id, e);
+        }
+        result?;
+
+        info!(
+            "OSDR sync run {} ({}) finished: {} pages, {} fetched, {} inserted, {} changed, {} unchanged, {} failed, {} duplicates, stopped_early={}, completed={}",
+            run_id, mode.as_str(), report.pages, report.fetched, report.inserted, report.changed, report.unchanged,
+            report.failed, report.duplicates, report.stopped_early, report.completed
+        );
+
+        Ok(report)
+    }
+
+    /// Журнал прогонов синхронизации OSDR
+    pub async fn sync_runs(&self, status: Option<&str>, limit: i64) -> Result<Vec<SyncRun>, ApiError> {
+        SyncRunRepository::list(&self.pool, OSDR_SYNC_SOURCE, status, limit).await
+    }
+
+    pub async fn sync_run(&self, id: i64) -> Result<SyncRun, ApiError> {
+        SyncRunRepository::get(&self.pool, OSDR_SYNC_SOURCE, id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Sync run {} not found", id)))
+    }
+
+    /// Режим по умолчанию: инкрементальный не доходит до конца выдачи, поэтому
+    /// незавершённый полный обход продолжается, а устаревший — запускается заново
+    async fn default_mode(&self) -> Result<OsdrSyncMode, ApiError> {
+        let config = self.client.config();
+        if config.osdr_sync_mode == OsdrSyncMode::Full {
+            return Ok(OsdrSyncMode::Full);
+        }
+        if let Some(cp) = OsdrSyncRepository::get(&self.pool).await? {
+            if cp.completed_at.is_none() && cp.next_from > 0 && cp.mode == OsdrSyncMode::Full.as_str() {
+                return Ok(OsdrSyncMode::Full);
+            }
+        }
+        let full_due = match SyncRunRepository::last_full_traversal(&self.pool, OSDR_SYNC_SOURCE).await? {
+            Some(finished_at) => Utc::now() - finished_at >= Duration::seconds(config.osdr_full_sync_every_seconds as i64),
+            None => true,
+        };
+        Ok(if full_due { OsdrSyncMode::Full } else { config.osdr_sync_mode })
+    }
+
+    /// Причина не помечать withdrawn: выдача заметно меньше сохранённого каталога
+    /// или пропала слишком большая доля записей
+    pub(crate) fn withdrawal_blocked(active: i64, total_hits: Option<i64>, unseen: i64) -> Option<String> {
+        if let Some(total) = total_hits {
+            if (total as f64) < active as f64 * OSDR_WITHDRAW_MIN_HITS_RATIO {
+                return Some(format!("hits.total {} is far below {} active datasets", total, active));
+            }
+        }
+        if active > 0 && unseen as f64 > active as f64 * OSDR_WITHDRAW_MAX_SHARE {
+            return Some(format!("{} of {} active datasets were not seen", unseen, active));
+        }
+        None
+    }
+
+    async fn sync_pages(&self, report: &mut OsdrSyncReport) -> Result<(), ApiError> {
+        let page_size = self.client.config().osdr_page_size;
+        let max_pages = self.client.config().osdr_max_pages;
+
+        // Обход может растянуться на несколько прогонов — tombstone считается от его начала
+        let (resumed_from, traversal_started_at) = match OsdrSyncRepository::get(&self.pool).await? {
+            Some(cp) if cp.completed_at.is_none() && cp.next_from > 0 && cp.mode == report.mode.as_str() => {
+                info!("OSDR sync resuming from offset {}", cp.next_from);
+                (cp.next_from, cp.started_at)
+            }
+            // Незавершённый обход в другом режиме не продолжаем — начинаем заново в запрошенном
+            Some(cp) if cp.completed_at.is_none() && cp.next_from > 0 => {
+                info!("OSDR sync restarting {} traversal as {}", cp.mode, report.mode.as_str());
+                (0, OsdrSyncRepository::begin(&self.pool, report.mode).await?)
+            }
+            _ => (0, OsdrSyncRepository::begin(&self.pool, report.mode).await?),
+        };
+        report.resumed_from = resumed_from;
+        let mut from = report.resumed_from;
+
+        while report.pages < max_pages {
+            let started = std::time::Instant::now();
+            let json = self.client.fetch_osdr(from, page_size).await?;
+            report.record_upstream_latency(started.elapsed().as_millis() as u64);
+            if let Some(total) = Self::hits_total(&json) {
+                report.total_hits = Some(total);
+            }
+            let items = self.parse_items_array(json);
+            let page_len = items.len();
+            if page_len == 0 {
+                report.completed = true;
+                break;
+            }
+
+            let ids: Vec<String> = items.iter().filter_map(Self::extract_dataset_id).collect();
+            let stored = OsdrRepository::release_dates(&self.pool, &ids).await?;
+
+            let unchanged = items
+                .iter()
+                .filter(|item| {
+                    let known = Self::extract_dataset_id(item).and_then(|id| stored.get(&id).copied()).flatten();
+                    known.is_some() && known == Self::extract_release_date(item)
+                })
+                .count();
+
+            // Невалидные записи не срывают пакет — попадают в отчёт как failed;
+            // повторы внутри страницы не ошибка и на withdrawal не влияют
+            let (rows, rejected, duplicates) = Self::prepare_rows(items);
+            if duplicates > 0 {
+                debug!("Dropped {} duplicate OSDR items within page at offset {}", duplicates, from);
+                report.duplicates += duplicates;
+            }
+            for rejection in rejected {
+                warn!("Skipping OSDR item {:?}: {}", rejection.dataset_id, rejection.message);
+                report.record_failure(rejection.dataset_id, rejection.message);
+            }
+            for outcome in OsdrRepository::upsert_batch(&self.pool, &rows).await? {
+                match outcome {
+                    OsdrUpsertOutcome::Inserted => report.inserted += 1,
+                    OsdrUpsertOutcome::Changed => report.changed += 1,
+                    OsdrUpsertOutcome::Unchanged => report.unchanged += 1,
+                }
+            }
+
+            from += page_len as i64;
+            report.pages += 1;
+            report.fetched += page_len;
+            OsdrSyncRepository::advance(&self.pool, from, report.total_hits).await?;
+
+            if report.mode == OsdrSyncMode::Incremental && unchanged == page_len {
+                report.stopped_early = true;
+                report.completed = true;
+                break;
+            }
+            if report.total_hits.is_some_and(|total| from >= total) || page_len < page_size as usize {
+                report.completed = true;
+                break;
+            }
+        }
+
+        if report.completed {
+            OsdrSyncRepository::complete(&self.pool).await?;
+
+            // Только полный непустой обход без ошибок: иначе непросмотренные записи не значат, что их убрали
+            let full_traversal = !report.stopped_early && from > 0;
+            if full_traversal && report.failed > 0 {
+                warn!("OSDR sync had {} failed items, skipping withdrawal of unseen datasets", report.failed);
+            } else if full_traversal {
+                let (active, unseen) = OsdrRepository::count_unseen(&self.pool, traversal_started_at).await?;
+                if let Some(reason) = Self::withdrawal_blocked(active, report.total_hits, unseen) {
+                    warn!("OSDR sync skipping withdrawal of unseen datasets: {}", reason);
+                } else if unseen > 0 {
+                    report.withdrawn = OsdrRepository::withdraw_unseen(&self.pool, traversal_started_at).await? as usize;
+                    info!("OSDR sync marked {} datasets as withdrawn", report.withdrawn);
+                }
+            }
+        } else {
+            warn!("OSDR sync stopped at page limit ({}), will resume from offset {}", max_pages, from);
+        }
+
+        Ok(())
+    }
+
+    /// `hits.total` бывает числом или объектом `{"value": N}` (Elasticsearch 7+)
+    pub(crate) fn hits_total(json: &Value) -> Option<i64> {
+        let total = json.get("hits")?.get("total")?;
+        total.as_i64().or_else(|| total.get("value").and_then(|v| v.as_i64()))
+    }
+
+    fn extract_dataset_id(item: &Value) -> Option<String> {
+        Self::pick_string(item, &[
             "Accession",  // OSDR Search API field
             "Study Identifier",
             "dataset_id", 
             "id", 
             "uuid", 
             "studyId"
-        ]);
-        
-        let title = Self::pick_string(&item, &[
-            "Study Title",  // OSDR Search API field
-            "title", 
-            "name", 
-            "label"
-        ]);
-        
-        let status = Self::pick_string(&item, &[
-            "Project Type",  // OSDR Search API field
-            "status", 
-            "state", 
-            "lifecycle"
-        ]);
-        
-        let updated = Self::pick_datetime(&item, &[
+        ])
+    }
+
+    fn extract_release_date(item: &Value) -> Option<DateTime<Utc>> {
+        Self::pick_datetime(item, &[
             "Study Public Release Date",
             "updated", 
             "updated_at", 
             "modified"
-        ]);
+        ])
+    }
 
-        OsdrRepository::upsert(&self.pool, id.as_deref(), title, status, updated, item).await?;
-        Ok(())
+    /// Извлечь поля страницы для пакетного upsert; отклонённые записи возвращаются отдельно.
+    /// Повтор ключа (dataset_id или содержимого без него) внутри страницы отбрасывается
+    /// и только подсчитывается: один INSERT не может обновить строку дважды.
+    pub(crate) fn prepare_rows(items: Vec<Value>) -> (Vec<OsdrUpsertRow>, Vec<OsdrSyncError>, usize) {
+        let mut rows: Vec<OsdrUpsertRow> = Vec::with_capacity(items.len());
+        let mut rejected = Vec::new();
+        let mut duplicates = 0;
+
+        for item in items {
+            // Extract fields from OSDR Search API _source
+            let dataset_id = Self::extract_dataset_id(&item);
+            let reject = |message: String| OsdrSyncError { dataset_id: dataset_id.clone(), message };
+
+            if !item.is_object() {
+                rejected.push(reject("item is not a JSON object".to_string()));
+                continue;
+            }
+            if let Some(id) = &dataset_id {
+                if let Err(e) = crate::validation::validate_dataset_id(id) {
+                    rejected.push(reject(e));
+                    continue;
+                }
+                if rows.iter().any(|r| r.dataset_id.as_ref() == Some(id)) {
+                    duplicates += 1;
+                    continue;
+                }
+            } else if rows.iter().any(|r| r.dataset_id.is_none() && r.raw == item) {
+                // Без dataset_id запись опознаётся по содержимому
+                duplicates += 1;
+                continue;
+            }
+
+            let title = Self::pick_string(&item, &[
+                "Study Title",  // OSDR Search API field
+                "title", 
+                "name", 
+                "label"
+            ]);
+            
+            let status = Self::pick_string(&item, &[
+                "Project Type",  // OSDR Search API field
+                "status", 
+                "state", 
+                "lifecycle"
+            ]);
+
+            rows.push(OsdrUpsertRow {
+                dataset_id,
+                title,
+                status,
+                updated_at: Self::extract_release_date(&item),
+                raw: item,
+            });
+        }
+
+        (rows, rejected, duplicates)
     }
 
     fn parse_items_array(&self, json: Value) -> Vec<Value> {
@@ -327,10 +1196,27 @@ impl SpaceService {
             }
             "spacex" => self.client.fetch_spacex_next().await?,
             "jwst" => self.client.fetch_jwst().await?,
+            "tle" => {
+                let payload = self.client.fetch_tle().await?;
+                // Не сохраняем TLE, который не разбирается SGP4
+                OrbitPropagator::from_payload(&payload)?;
+                payload
+            }
             _ => return Err(ApiError::bad_request("INVALID_SOURCE", "Unknown source")),
         };
+        crate::validation::validate_json_payload(&payload)
+            .map_err(|e| ApiError::upstream_error(format!("Malformed {} payload: {}", source, e)))?;
+
+        // Расхождение схемы не блокирует сохранение, но фиксируется у снимка
+        let schema_warning = match Self::normalize(source, &payload) {
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Schema drift in {} payload: {}", source, e);
+                Some(e)
+            }
+        };
 
-        let cache = CacheRepository::save(&self.pool, source, payload).await?;
+        let cache = CacheRepository::save(&self.pool, source, payload, schema_warning.as_deref()).await?;
         
         // Инвалидируем Redis кэш для этого источника
         let _ = self.cache.delete(&cache_keys::space_latest(source)).await;
@@ -339,6 +1225,81 @@ impl SpaceService {
         Ok(cache)
     }
 
+    /// Разобрать payload источника в типизированную модель; ошибка — путь и причина расхождения
+    pub(crate) fn normalize(source: &str, payload: &Value) -> Result<SpacePayload, String> {
+        match source {
+            "apod" => ApodEntry::from_payload(payload).map(SpacePayload::Apod),
+            "neo" => NeoFeed::from_payload(payload).map(SpacePayload::Neo),
+            "flr" => DonkiFlare::list_from_payload(payload).map(SpacePayload::Flares),
+            "cme" => DonkiCme::list_from_payload(payload).map(SpacePayload::Cme),
+            "spacex" => SpaceXLaunch::from_payload(payload).map(SpacePayload::SpaceX),
+            "jwst" => JwstImage::list_from_payload(payload).map(SpacePayload::Jwst),
+            "tle" => {
+                let orbit = OrbitPropagator::from_payload(payload).map_err(|e| e.to_string())?;
+                let line = |key: &str| payload.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                Ok(SpacePayload::Tle(TleSet {
+                    name: payload
+                        .get("header")
+                        .or_else(|| payload.get("name"))
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.trim().to_string()),
+                    norad_id: orbit.norad_id(),
+                    epoch: orbit.epoch(),
+                    line1: line("line1"),
+                    line2: line("line2"),
+                }))
+            }
+            _ => Err(format!("no typed model for source `{}`", source)),
+        }
+    }
+
+    /// История снимков источника; лишняя строка — признак следующей страницы
+    pub async fn history(&self, source: &str, filter: &SpaceHistoryFilter) -> Result<SpaceHistoryPage, ApiError> {
+        let mut items = CacheRepository::history(&self.pool, source, filter, filter.limit + 1).await?;
+        let next_cursor = if items.len() as i64 > filter.limit {
+            items.truncate(filter.limit as usize);
+            items.last().map(|last| SpaceHistoryCursor::after(last).encode())
+        } else {
+            None
+        };
+        Ok(SpaceHistoryPage { source: source.to_string(), items, next_cursor })
+    }
+
+    /// Снимок, который был актуален на момент `ts`
+    pub async fn snapshot_at(&self, source: &str, ts: DateTime<Utc>) -> Result<SpaceCache, ApiError> {
+        CacheRepository::get_at(&self.pool, source, ts)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("No {} snapshot at or before {}", source, ts.to_rfc3339())))
+    }
+
+    /// Diff двух снимков источника: по умолчанию `to` — последний, `from` — предыдущий перед `to`
+    pub async fn diff(&self, source: &str, from_id: Option<i64>, to_id: Option<i64>) -> Result<SpaceDiff, ApiError> {
+        let missing = |id: i64| ApiError::not_found(format!("Snapshot {} of {} not found", id, source));
+
+        let to = match to_id {
+            Some(id) => CacheRepository::get(&self.pool, source, id).await?.ok_or_else(|| missing(id))?,
+            None => CacheRepository::get_latest(&self.pool, source)
+                .await?
+                .ok_or_else(|| ApiError::not_found(format!("No snapshots of {}", source)))?,
+        };
+        let from = match from_id {
+            Some(id) => CacheRepository::get(&self.pool, source, id).await?.ok_or_else(|| missing(id))?,
+            None => CacheRepository::get_previous(&self.pool, source, to.id)
+                .await?
+                .ok_or_else(|| ApiError::not_found(format!("No snapshot of {} before {}", source, to.id)))?,
+        };
+
+        let patch = diff::json_patch(&from.payload, &to.payload);
+        let summary = diff::summarize(&from.payload, &patch);
+        Ok(SpaceDiff {
+            source: source.to_string(),
+            from: SpaceSnapshotRef::from(&from),
+            to: SpaceSnapshotRef::from(&to),
+            patch,
+            summary,
+        })
+    }
+
     /// Получить summary всех источников
     pub async fn summary(&self) -> Result<Value, ApiError> {
         // Проверяем кэш
@@ -357,7 +1318,7 @@ impl SpaceService {
             count
         };
         
-        let iss_last = IssRepository::get_last(&self.pool).await?;
+        let iss_last = IssRepository::get_last(&self.pool, ISS_NORAD_ID).await?;
 
         let mut data = serde_json::json!({
             "osdr_count": osdr_count,
@@ -390,3 +1351,137 @@ impl SpaceService {
         (from.to_string(), to.to_string())
     }
 }
+
+/// Retention Service - политики хранения iss_fetch_log и space_cache
+#[derive(Clone)]
+pub struct RetentionService {
+    pool: PgPool,
+    config: Config,
+}
+
+impl RetentionService {
+    pub fn new(pool: PgPool, config: Config) -> Self {
+        Self { pool, config }
+    }
+
+    /// Действующие политики и последние прогоны
+    pub async fn status(&self) -> Result<RetentionStatus, ApiError> {
+        Ok(RetentionStatus {
+            every_seconds: self.config.retention_every_seconds,
+            batch_size: self.config.retention_batch_size,
+            iss: self.config.iss_retention,
+            space_default: self.config.space_retention,
+            space_overrides: self.config.space_retention_overrides.clone(),
+            runs: RetentionRepository::list(&self.pool, RETENTION_RUNS_SHOWN).await?,
+        })
+    }
+
+    /// Применить политики ко всем таблицам; итог пишется в retention_runs.
+    /// Фоновый и ручной прогоны (в том числе разных экземпляров) не идут одновременно:
+    /// если прогон уже идёт, возвращается 409 RETENTION_RUNNING.
+    pub async fn run(&self, trigger: SyncTrigger) -> Result<RetentionRun, ApiError> {
+        self.try_run(trigger).await?.ok_or_else(|| {
+            ApiError::new("RETENTION_RUNNING", "Retention run is already in progress")
+                .with_status(axum::http::StatusCode::CONFLICT)
+        })
+    }
+
+    /// Как `run`, но занятый lock — не ошибка: `None`, прогон пропущен
+    pub async fn try_run(&self, trigger: SyncTrigger) -> Result<Option<RetentionRun>, ApiError> {
+        let Some(lock) = RetentionRepository::try_lock(&self.pool).await? else {
+            return Ok(None);
+        };
+
+        let result = self.run_locked(trigger).await;
+        RetentionRepository::unlock(lock).await;
+        result.map(Some)
+    }
+
+    async fn run_locked(&self, trigger: SyncTrigger) -> Result<RetentionRun, ApiError> {
+        let run_id = RetentionRepository::start(&self.pool, trigger).await?;
+        let mut scopes = Vec::new();
+        let result = self.apply_all(&mut scopes).await;
+
+        let run_error = result.as_ref().err().map(|e| e.to_string());
+        let run = RetentionRepository::finish(&self.pool, run_id, &scopes, run_error.as_deref()).await?;
+        result?;
+
+        info!(
+            "Retention run {} finished: {} compacted, {} dropped across {} scopes",
+            run.id, run.compacted, run.dropped, scopes.len()
+        );
+        Ok(run)
+    }
+
+    async fn apply_all(&self, scopes: &mut Vec<RetentionScopeReport>) -> Result<(), ApiError> {
+        let now = Utc::now();
+        let mut targets = vec![(RetentionTable::IssFetchLog, None, self.config.iss_retention)];
+        for source in RetentionRepository::space_sources(&self.pool).await? {
+            let policy = self.config.space_retention_for(&source);
+            targets.push((RetentionTable::SpaceCache, Some(source), policy));
+        }
+
+        for (table, scope, policy) in targets {
+            let mut report = RetentionScopeReport::new(table, scope.as_deref(), policy);
+            let result = self.apply(table, scope.as_deref(), &mut report, now).await;
+            scopes.push(report);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Политика для одной таблицы/источника: пакетами, пока пакет заполняется целиком
+    async fn apply(
+        &self,
+        table: RetentionTable,
+        scope: Option<&str>,
+        report: &mut RetentionScopeReport,
+        now: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let batch = self.config.retention_batch_size;
+        let (compact_before, drop_before) = report.policy.cutoffs(now);
+
+        // Сначала удаляем устаревшее — прореживать его незачем
+        if let Some(before) = drop_before {
+            loop {
+                let deleted = RetentionRepository::drop_batch(&self.pool, table, scope, before, batch).await?;
+                report.dropped += deleted;
+                report.batches += 1;
+                if (deleted as i64) < batch {
+                    break;
+                }
+            }
+        }
+
+        // Уровень обходится окнами от старых записей к новым: пакет ранжирует только своё окно,
+        // а пустые промежутки пропускаются переходом к следующей существующей записи
+        let mut next = RetentionRepository::oldest_since(&self.pool, table, scope, drop_before, compact_before).await?;
+        while let Some(at) = next {
+            let (window_start, window_end) = report.policy.compaction_window(at);
+            let from = drop_before.map_or(window_start, |d| d.max(window_start));
+            let before = window_end.min(compact_before);
+            loop {
+                let deleted = RetentionRepository::compact_batch(
+                    &self.pool,
+                    table,
+                    scope,
+                    Some(from),
+                    before,
+                    report.policy.bucket_secs,
+                    batch,
+                )
+                .await?;
+                report.compacted += deleted;
+                report.batches += 1;
+                if (deleted as i64) < batch {
+                    break;
+                }
+            }
+            if before >= compact_before {
+                break;
+            }
+            next = RetentionRepository::oldest_since(&self.pool, table, scope, Some(before), compact_before).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use crate::domain::{EclipseEvent, EclipseEventKind, Illumination};
+use crate::orbit::{geodetic_to_ecef, gmst_rad, normalize_lon, sun_position_eci, teme_to_ecef, EARTH_RADIUS_KM};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+
+/// Радиус Солнца, км
+const SUN_RADIUS_KM: f64 = 696_000.0;
+
+/// Пары замеров дальше друг от друга не используем для поиска входа/выхода из тени:
+/// тень МКС длится ~35 минут, через длинный пропуск можно пропустить целое затмение
+pub const ECLIPSE_MAX_SAMPLE_GAP_SEC: f64 = 600.0;
+
+/// Шаг по долготе для полигона ночной стороны, градусы
+const TERMINATOR_STEP_DEG: f64 = 2.0;
+
+/// Положение Солнца в ECEF, км
+pub fn sun_position_ecef(at: DateTime<Utc>) -> [f64; 3] {
+    teme_to_ecef(sun_position_eci(at), gmst_rad(at))
+}
+
+/// Подсолнечная точка (широта, долгота), градусы
+pub fn subsolar_point(at: DateTime<Utc>) -> (f64, f64) {
+    let s = sun_position_ecef(at);
+    let lat = s[2].atan2((s[0] * s[0] + s[1] * s[1]).sqrt()).to_degrees();
+    let lon = normalize_lon(s[1].atan2(s[0]).to_degrees());
+    (lat, lon)
+}
+
+/// Угловые величины, видимые со спутника (радианы)
+struct ShadowGeometry {
+    /// Угол между направлениями на центр Солнца и центр Земли
+    separation: f64,
+    earth_radius: f64,
+    sun_radius: f64,
+}
+
+impl ShadowGeometry {
+    fn new(lat: f64, lon: f64, alt_km: f64, at: DateTime<Utc>) -> Self {
+        let sat = geodetic_to_ecef(lat, lon, alt_km);
+        let sun = sun_position_ecef(at);
+        let to_sun = [sun[0] - sat[0], sun[1] - sat[1], sun[2] - sat[2]];
+        let d_sun = norm(to_sun);
+        let d_earth = norm(sat);
+
+        let cos_sep = -(to_sun[0] * sat[0] + to_sun[1] * sat[1] + to_sun[2] * sat[2]) / (d_sun * d_earth);
+        Self {
+            separation: cos_sep.clamp(-1.0, 1.0).acos(),
+            earth_radius: (EARTH_RADIUS_KM / d_earth).min(1.0).asin(),
+            sun_radius: (SUN_RADIUS_KM / d_sun).asin(),
+        }
+    }
+
+    /// Запас до границы полной тени: отрицательный — спутник в тени
+    fn umbra_margin(&self) -> f64 {
+        self.separation - (self.earth_radius - self.sun_radius)
+    }
+
+    fn illumination(&self) -> Illumination {
+        if self.separation >= self.earth_radius + self.sun_radius {
+            Illumination::Daylight
+        } else if self.umbra_margin() <= 0.0 {
+            Illumination::Eclipse
+        } else {
+            Illumination::Penumbra
+        }
+    }
+}
+
+fn norm(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+/// Освещённость спутника (коническая модель тени: тень, полутень, Солнце)
+pub fn illumination(lat: f64, lon: f64, alt_km: f64, at: DateTime<Utc>) -> Illumination {
+    ShadowGeometry::new(lat, lon, alt_km, at).illumination()
+}
+
+/// Входы в тень и выходы из неё по упорядоченным замерам (время, широта, долгота, высота).
+/// Момент события — линейная интерполяция запаса до границы тени между соседними замерами.
+pub fn eclipse_events(samples: &[(DateTime<Utc>, f64, f64, f64)]) -> Vec<EclipseEvent> {
+    let margins: Vec<(DateTime<Utc>, f64)> = samples
+        .iter()
+        .map(|&(at, lat, lon, alt)| (at, ShadowGeometry::new(lat, lon, alt, at).umbra_margin()))
+        .collect();
+
+    let mut events = Vec::new();
+    for pair in margins.windows(2) {
+        let ((t0, m0), (t1, m1)) = (pair[0], pair[1]);
+        let dt = (t1 - t0).num_milliseconds() as f64 / 1000.0;
+        if dt <= 0.0 || dt > ECLIPSE_MAX_SAMPLE_GAP_SEC {
+            continue;
+        }
+
+        let kind = match (m0 > 0.0, m1 > 0.0) {
+            (true, false) => EclipseEventKind::Entry,
+            (false, true) => EclipseEventKind::Exit,
+            _ => continue,
+        };
+        let fraction = m0 / (m0 - m1);
+        events.push(EclipseEvent {
+            kind,
+            at: t0 + Duration::milliseconds((dt * fraction * 1000.0) as i64),
+            prev_sample_at: t0,
+            next_sample_at: t1,
+        });
+    }
+    events
+}
+
+/// Ночная сторона Земли на момент `at` в виде GeoJSON: полигон + подсолнечная точка
+pub fn terminator_geojson(at: DateTime<Utc>) -> Value {
+    let (sun_lat, sun_lon) = subsolar_point(at);
+    // В равноденствие терминатор проходит через полюса — избегаем деления на ноль
+    let tan_sun_lat = sun_lat.to_radians().tan();
+    let tan_sun_lat = if tan_sun_lat.abs() < 1e-6 { 1e-6_f64.copysign(tan_sun_lat) } else { tan_sun_lat };
+
+    let steps = (360.0 / TERMINATOR_STEP_DEG) as usize;
+    let mut ring: Vec<[f64; 2]> = (0..=steps)
+        .map(|i| {
+            let lon = -180.0 + i as f64 * TERMINATOR_STEP_DEG;
+            let dlon = (lon - sun_lon).to_radians();
+            let lat = (-dlon.cos() / tan_sun_lat).atan().to_degrees();
+            [lon, lat]
+        })
+        .collect();
+
+    // Замыкаем через полюс, где сейчас полярная ночь
+    let dark_pole = if sun_lat >= 0.0 { -90.0 } else { 90.0 };
+    ring.push([180.0, dark_pole]);
+    ring.push([-180.0, dark_pole]);
+    ring.push(ring[0]);
+    // RFC 7946: внешнее кольцо против часовой стрелки
+    if dark_pole < 0.0 {
+        ring.reverse();
+    }
+
+    json!({
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "geometry": {"type": "Polygon", "coordinates": [ring]},
+                "properties": {"kind": "night", "at": at}
+            },
+            {
+                "type": "Feature",
+                "geometry": {"type": "Point", "coordinates": [sun_lon, sun_lat]},
+                "properties": {"kind": "subsolar", "at": at}
+            }
+        ]
+    })
+}
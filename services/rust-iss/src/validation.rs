@@ -1,11 +1,106 @@
-use validator::Validate;
+use crate::domain::{GeofenceShape, OsdrCursor, OsdrFacetFilter, OsdrListFilter, OsdrSortBy, SpaceHistoryCursor, SpaceHistoryFilter};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
 use serde_json::Value;
 
-/// Валидация параметров запроса ISS
+/// Валидация параметров /iss/trend (окно в минутах, до суток)
 #[derive(Debug, Validate)]
-pub struct IssQueryParams {
-    #[validate(length(min = 1, max = 500))]
-    pub source: Option<String>,
+pub struct IssTrendParams {
+    #[validate(range(min = 1, max = 1440))]
+    pub window: Option<i64>,
+}
+
+/// Валидация параметров /iss/history
+#[derive(Debug, Validate)]
+pub struct IssHistoryParams {
+    #[validate(range(min = 60, max = 86400))]
+    pub step: Option<i64>,
+}
+
+/// Валидация параметров /iss/passes (высота наблюдателя в метрах)
+#[derive(Debug, Validate)]
+pub struct IssPassesParams {
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub lat: f64,
+
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub lon: f64,
+
+    #[validate(range(min = -500.0, max = 9000.0))]
+    pub alt: Option<f64>,
+
+    #[validate(range(min = 1, max = 10))]
+    pub days: Option<i64>,
+}
+
+/// Тело POST/PUT /iss/geofences
+#[derive(Debug, Deserialize, Validate)]
+pub struct GeofencePayload {
+    #[validate(length(min = 1, max = 200))]
+    pub name: String,
+
+    #[validate(range(min = 1))]
+    pub norad_id: Option<i32>,
+
+    #[validate(custom(function = "validate_geofence_shape"))]
+    pub shape: GeofenceShape,
+
+    pub enabled: Option<bool>,
+}
+
+/// Валидация параметров /iss/geofence/events
+#[derive(Debug, Validate)]
+pub struct GeofenceEventsParams {
+    #[validate(range(min = 1, max = 1000))]
+    pub limit: Option<i64>,
+}
+
+/// Параметры журнала прогонов синхронизации
+#[derive(Debug, Deserialize, Validate)]
+pub struct SyncRunsParams {
+    #[validate(range(min = 1, max = 200))]
+    pub limit: Option<i64>,
+    #[validate(custom(function = "validate_sync_run_status"))]
+    pub status: Option<String>,
+}
+
+fn validate_sync_run_status(status: &str) -> Result<(), ValidationError> {
+    match status {
+        "running" | "succeeded" | "failed" | "interrupted" => Ok(()),
+        _ => Err(ValidationError::new("invalid_status")),
+    }
+}
+
+/// Максимум вершин полигона геозоны
+const GEOFENCE_MAX_VERTICES: usize = 1000;
+/// Максимальный радиус круговой геозоны, км
+const GEOFENCE_MAX_RADIUS_KM: f64 = 5000.0;
+
+/// Проверка формы геозоны: координаты в допустимых пределах, у полигона минимум 3 вершины
+pub fn validate_geofence_shape(shape: &GeofenceShape) -> Result<(), ValidationError> {
+    let valid_point = |lat: f64, lon: f64| (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon);
+    let error = |msg: &'static str| Err(ValidationError::new("geofence_shape").with_message(msg.into()));
+
+    match shape {
+        GeofenceShape::Circle { lat, lon, radius_km } => {
+            if !valid_point(*lat, *lon) {
+                return error("circle center is out of range");
+            }
+            if !(*radius_km > 0.0 && *radius_km <= GEOFENCE_MAX_RADIUS_KM) {
+                return error("radius_km must be in (0, 5000]");
+            }
+        }
+        GeofenceShape::Polygon { coordinates } => {
+            if coordinates.len() < 3 || coordinates.len() > GEOFENCE_MAX_VERTICES {
+                return error("polygon must have 3..1000 vertices");
+            }
+            if !coordinates.iter().all(|&[lon, lat]| valid_point(lat, lon)) {
+                return error("polygon vertex is out of range");
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Валидация параметров запроса OSDR
@@ -25,6 +120,69 @@ pub struct OsdrQueryParams {
     
     #[validate(length(min = 1, max = 10))]
     pub sort_order: Option<String>,
+
+    #[validate(length(min = 1, max = 1000))]
+    pub cursor: Option<String>,
+
+    #[validate(length(min = 1, max = 200))]
+    pub organism: Option<String>,
+
+    #[validate(length(min = 1, max = 200))]
+    pub project_type: Option<String>,
+
+    #[validate(length(min = 1, max = 200))]
+    pub assay_technology: Option<String>,
+
+    #[validate(range(min = 1900, max = 2100))]
+    pub release_year: Option<i64>,
+
+    pub include_withdrawn: bool,
+}
+
+impl OsdrQueryParams {
+    /// Собрать фильтр списка: сортировка только по whitelist, пустые строки игнорируются
+    pub fn into_filter(self) -> Result<OsdrListFilter, String> {
+        let sort_by = match self.sort_by.as_deref() {
+            None => OsdrSortBy::InsertedAt,
+            Some(s) => OsdrSortBy::parse(s)
+                .ok_or_else(|| format!("`sort_by` must be one of: {}", OsdrSortBy::ALLOWED))?,
+        };
+        let descending = match self.sort_order.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            None | Some("desc") => true,
+            Some("asc") => false,
+            Some(_) => return Err("`sort_order` must be `asc` or `desc`".to_string()),
+        };
+        let non_empty = |v: Option<String>| v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+
+        // Курсор действителен только для той сортировки, в которой был выдан
+        let after = self.cursor.as_deref().map(OsdrCursor::decode).transpose()?;
+        if let Some(cursor) = &after {
+            if cursor.sort_by != sort_by || cursor.descending != descending {
+                return Err("`cursor` does not match `sort_by`/`sort_order`".to_string());
+            }
+            // Значение попадает в CAST(... AS timestamptz) — проверяем заранее, а не ловим ошибку БД
+            let is_time_sort = matches!(cursor.sort_by, OsdrSortBy::InsertedAt | OsdrSortBy::UpdatedAt);
+            if is_time_sort && cursor.value != "-infinity" && DateTime::parse_from_rfc3339(&cursor.value).is_err() {
+                return Err("malformed cursor".to_string());
+            }
+        }
+
+        Ok(OsdrListFilter {
+            status: non_empty(self.status),
+            search: non_empty(self.search),
+            sort_by,
+            descending,
+            limit: self.limit.unwrap_or(20).clamp(1, 100),
+            after,
+            facets: OsdrFacetFilter {
+                organism: non_empty(self.organism),
+                project_type: non_empty(self.project_type),
+                assay_technology: non_empty(self.assay_technology),
+                release_year: self.release_year.map(|y| y as i32),
+            },
+            include_withdrawn: self.include_withdrawn,
+        })
+    }
 }
 
 /// Валидация параметров Space Cache
@@ -34,6 +192,47 @@ pub struct SpaceCacheQueryParams {
     pub src: Option<String>,
 }
 
+/// Параметры истории снимков /space/:src/history
+#[derive(Debug, Validate)]
+pub struct SpaceHistoryParams {
+    #[validate(range(min = 1, max = 200))]
+    pub limit: Option<i64>,
+
+    #[validate(length(min = 1, max = 200))]
+    pub cursor: Option<String>,
+
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub include_payload: bool,
+}
+
+impl SpaceHistoryParams {
+    pub fn into_filter(self) -> Result<SpaceHistoryFilter, String> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err("`from` must not be after `to`".to_string());
+            }
+        }
+        Ok(SpaceHistoryFilter {
+            from: self.from,
+            to: self.to,
+            limit: self.limit.unwrap_or(50),
+            after: self.cursor.as_deref().map(SpaceHistoryCursor::decode).transpose()?,
+            include_payload: self.include_payload,
+        })
+    }
+}
+
+/// Параметры /space/:src/diff (id снимков space_cache)
+#[derive(Debug, Validate)]
+pub struct SpaceDiffParams {
+    #[validate(range(min = 1))]
+    pub from_id: Option<i64>,
+
+    #[validate(range(min = 1))]
+    pub to_id: Option<i64>,
+}
+
 /// Базовая валидация JSON payload'а
 pub fn validate_json_payload(payload: &Value) -> Result<(), String> {
     if !payload.is_object() && !payload.is_array() {
@@ -67,6 +266,29 @@ pub fn validate_source_id(source: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Проверка dataset_id из пути (например `OSD-379`)
+pub fn validate_dataset_id(dataset_id: &str) -> Result<(), String> {
+    if dataset_id.is_empty() || dataset_id.len() > 100 {
+        return Err("Invalid dataset id".to_string());
+    }
+    if !dataset_id.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+        return Err("Dataset id may contain only letters, digits, _, -, .".to_string());
+    }
+    Ok(())
+}
+
+/// Разбор момента времени: RFC 3339 или unix-время в секундах
+pub fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    value
+        .parse::<i64>()
+        .ok()
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .ok_or_else(|| format!("Некорректная метка времени: {}", value))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;